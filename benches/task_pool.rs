@@ -0,0 +1,142 @@
+#![feature(test)]
+
+extern crate test;
+
+extern crate pi_lib;
+extern crate pi_base;
+
+use test::Bencher;
+
+use pi_lib::atom::Atom;
+use pi_base::task::{TaskType, Task};
+use pi_base::task_pool::TaskPool;
+use pi_base::policy::{SchedulingPolicy, WeightedRandomPolicy, StrictPriorityPolicy, WeightedRoundRobinPolicy, EarliestDeadlinePolicy};
+
+//构建使用指定调度策略，且已有指定数量任务的任务池
+fn init_pool(task_type: TaskType, len: u64, policy: Box<SchedulingPolicy>) -> TaskPool {
+    let mut pool = TaskPool::new(10);
+    pool.set_policy(policy);
+    for i in 0..len {
+        pool.push(task_type, 1 + i % 100, Box::new(|| {}), Atom::from("bench task")).unwrap();
+    }
+    pool
+}
+
+//在使用指定调度策略，且已有指定数量任务的任务池中，加入、弹出并完成一个任务
+fn policy_push_pop(b: &mut Bencher, task_type: TaskType, len: u64, policy: Box<SchedulingPolicy>) {
+    let mut pool = init_pool(task_type, len, policy);
+    let mut task = Task::new();
+    let info = Atom::from("bench task");
+    b.iter(|| {
+        pool.push(task_type, 50, Box::new(|| {}), info.clone()).unwrap();
+        if pool.pop(&mut task) {
+            pool.complete(&mut task);
+        }
+        task.reset();
+    });
+}
+
+//在使用默认调度策略，且已有指定数量任务的任务池中，加入、弹出并完成一个任务
+fn push_pop(b: &mut Bencher, task_type: TaskType, len: u64) {
+    policy_push_pop(b, task_type, len, Box::new(WeightedRandomPolicy::new()));
+}
+
+#[bench]
+fn bench_async_push_pop_1000(b: &mut Bencher) {
+    push_pop(b, TaskType::Async, 1000);
+}
+
+#[bench]
+fn bench_async_push_pop_10000(b: &mut Bencher) {
+    push_pop(b, TaskType::Async, 10000);
+}
+
+#[bench]
+fn bench_async_push_pop_100000(b: &mut Bencher) {
+    push_pop(b, TaskType::Async, 100000);
+}
+
+#[bench]
+fn bench_async_push_pop_1000000(b: &mut Bencher) {
+    push_pop(b, TaskType::Async, 1000000);
+}
+
+#[bench]
+fn bench_sync_push_pop_1000(b: &mut Bencher) {
+    push_pop(b, TaskType::Sync, 1000);
+}
+
+#[bench]
+fn bench_sync_push_pop_10000(b: &mut Bencher) {
+    push_pop(b, TaskType::Sync, 10000);
+}
+
+#[bench]
+fn bench_sync_push_pop_100000(b: &mut Bencher) {
+    push_pop(b, TaskType::Sync, 100000);
+}
+
+#[bench]
+fn bench_sync_push_pop_1000000(b: &mut Bencher) {
+    push_pop(b, TaskType::Sync, 1000000);
+}
+
+#[bench]
+fn bench_async_strict_push_pop_10000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Async, 10000, Box::new(StrictPriorityPolicy::new()));
+}
+
+#[bench]
+fn bench_async_strict_push_pop_100000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Async, 100000, Box::new(StrictPriorityPolicy::new()));
+}
+
+#[bench]
+fn bench_sync_strict_push_pop_10000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Sync, 10000, Box::new(StrictPriorityPolicy::new()));
+}
+
+#[bench]
+fn bench_sync_strict_push_pop_100000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Sync, 100000, Box::new(StrictPriorityPolicy::new()));
+}
+
+#[bench]
+fn bench_async_round_robin_push_pop_10000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Async, 10000, Box::new(WeightedRoundRobinPolicy::new()));
+}
+
+#[bench]
+fn bench_async_round_robin_push_pop_100000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Async, 100000, Box::new(WeightedRoundRobinPolicy::new()));
+}
+
+#[bench]
+fn bench_sync_round_robin_push_pop_10000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Sync, 10000, Box::new(WeightedRoundRobinPolicy::new()));
+}
+
+#[bench]
+fn bench_sync_round_robin_push_pop_100000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Sync, 100000, Box::new(WeightedRoundRobinPolicy::new()));
+}
+
+#[bench]
+fn bench_async_deadline_push_pop_10000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Async, 10000, Box::new(EarliestDeadlinePolicy::new(1000)));
+}
+
+#[bench]
+fn bench_async_deadline_push_pop_100000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Async, 100000, Box::new(EarliestDeadlinePolicy::new(1000)));
+}
+
+#[bench]
+fn bench_sync_deadline_push_pop_10000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Sync, 10000, Box::new(EarliestDeadlinePolicy::new(1000)));
+}
+
+#[bench]
+fn bench_sync_deadline_push_pop_100000(b: &mut Bencher) {
+    policy_push_pop(b, TaskType::Sync, 100000, Box::new(EarliestDeadlinePolicy::new(1000)));
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use std::cell::RefCell;
+use std::sync::atomic::{Ordering, AtomicUsize};
+use std::fmt::{Debug, Formatter, Result};
+
+use fnv::FnvHashMap;
+
+use pi_lib::atom::Atom;
+
+/*
+* 跟踪id计数器
+*/
+static TRACE_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static CURRENT_CONTEXT: RefCell<Option<TaskContext>> = RefCell::new(None); //当前线程的任务上下文
+}
+
+/*
+* 任务上下文内部数据
+*/
+struct ContextInner {
+    trace_id:   u64,                        //跟踪id
+    values:     FnvHashMap<Atom, Atom>,     //键值表
+}
+
+/*
+* 任务上下文，包括跟踪id和键值表，任务加入任务池时捕获当前线程的上下文，任务执行时安装到工作者线程，
+* 所以任务中投递的任务、异步文件操作的回调和未来任务都能获取到发起请求的上下文，上下文不可修改，复制的开销很小
+*/
+#[derive(Clone)]
+pub struct TaskContext {
+    inner:  Arc<ContextInner>,  //内部数据
+}
+
+impl Debug for TaskContext {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "TaskContext[trace_id = {}, values = {:?}]", self.inner.trace_id, self.inner.values)
+	}
+}
+
+impl TaskContext {
+    //构建一个指定跟踪id的上下文
+    pub fn new(trace_id: u64) -> Self {
+        TaskContext {
+            inner: Arc::new(ContextInner {
+                trace_id:   trace_id,
+                values:     FnvHashMap::default(),
+            }),
+        }
+    }
+
+    //构建一个使用新跟踪id的上下文，跟踪id从1开始，进程内唯一
+    pub fn with_new_trace() -> Self {
+        TaskContext::new(TRACE_ID.fetch_add(1, Ordering::Relaxed) as u64 + 1)
+    }
+
+    //获取跟踪id
+    pub fn get_trace_id(&self) -> u64 {
+        self.inner.trace_id
+    }
+
+    //获取指定键的值
+    pub fn get(&self, key: &Atom) -> Option<&Atom> {
+        self.inner.values.get(key)
+    }
+
+    //获取键值数量
+    pub fn len(&self) -> usize {
+        self.inner.values.len()
+    }
+
+    //构建一个增加了指定键值的新上下文，键已存在则替换值
+    pub fn with_value(&self, key: Atom, value: Atom) -> Self {
+        let mut values = self.inner.values.clone();
+        values.insert(key, value);
+        TaskContext {
+            inner: Arc::new(ContextInner {
+                trace_id:   self.inner.trace_id,
+                values:     values,
+            }),
+        }
+    }
+}
+
+/*
+* 上下文守护，释放时恢复当前线程原来的上下文，保证任务发生异常时也能恢复
+*/
+struct ContextGuard {
+    old:    Option<TaskContext>,    //原来的上下文
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        set_current(self.old.take());
+    }
+}
+
+/*
+* 获取当前线程的上下文
+*/
+pub fn current() -> Option<TaskContext> {
+    CURRENT_CONTEXT.with(|current| current.borrow().clone())
+}
+
+/*
+* 设置当前线程的上下文，返回原来的上下文
+*/
+pub fn set_current(context: Option<TaskContext>) -> Option<TaskContext> {
+    CURRENT_CONTEXT.with(|current| {
+        let mut current = current.borrow_mut();
+        let old = current.take();
+        *current = context;
+        old
+    })
+}
+
+/*
+* 在指定上下文中执行函数，执行结束或发生异常后恢复当前线程原来的上下文
+*/
+pub fn run_with<R, F: FnOnce() -> R>(context: Option<TaskContext>, f: F) -> R {
+    let _guard = ContextGuard {
+        old:    set_current(context),
+    };
+    f()
+}
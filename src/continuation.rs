@@ -0,0 +1,145 @@
+use std::boxed::FnBox;
+use std::time::{Instant, Duration};
+use std::sync::{Arc, Mutex, Condvar};
+use std::result::Result as GenResult;
+use std::fmt::{Display, Formatter, Result};
+
+use pi_lib::atom::Atom;
+use task::TaskType;
+use task_pool::{TaskPool, push_task};
+use context::{TaskContext, current, run_with};
+use pi_base_impl::is_steal_backend;
+
+/*
+* 可恢复任务的执行结果
+*/
+pub enum Resume {
+    Done,                                   //任务已完成
+    Yield(Box<FnBox() -> Resume + Send>),   //任务主动让出工作者，后续部分以原任务类型和优先级重新加入任务池
+}
+
+/*
+* 时间片，用于长任务判断是否应该让出工作者
+*/
+#[derive(Debug, Copy, Clone)]
+pub struct TimeSlice {
+    start:  Instant,    //时间片开始的时间
+    slice:  Duration,   //时间片时长
+}
+
+impl Display for TimeSlice {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "TimeSlice[elapsed = {:?}, slice = {:?}]", self.start.elapsed(), self.slice)
+	}
+}
+
+impl TimeSlice {
+    //构建一个从现在开始的指定时长的时间片
+    pub fn new(slice: Duration) -> Self {
+        TimeSlice {
+            start:  Instant::now(),
+            slice:  slice,
+        }
+    }
+
+    //获取时间片剩余时长
+    pub fn remaining(&self) -> Duration {
+        let elapsed = self.start.elapsed();
+        if elapsed >= self.slice {
+            Duration::new(0, 0)
+        } else {
+            self.slice - elapsed
+        }
+    }
+
+    //判断时间片是否已用完
+    pub fn is_expired(&self) -> bool {
+        self.start.elapsed() >= self.slice
+    }
+}
+
+/*
+* 可恢复任务的调度信息，后续部分以相同的调度信息重新加入同一个任务池
+*/
+#[derive(Clone)]
+struct ResumeInfo {
+    sync:       Arc<(Mutex<TaskPool>, Condvar)>,    //任务池
+    task_type:  TaskType,                           //任务类型
+    priority:   u64,                                //任务优先级
+    info:       Atom,                               //任务信息
+    tags:       Vec<Atom>,                          //任务标签
+    sheddable:  bool,                               //是否可丢弃
+    context:    Option<TaskContext>,                //加入第一段任务时的上下文
+}
+
+/*
+* 线程安全的向指定任务池加入一个可恢复任务，返回第一段任务的唯一id，任务返回让出时，后续部分以原任务类型、优先级和任务信息重新加入同一个任务池，
+* 后续部分和第一段任务一样受任务池容量、满时处理策略和排空模式限制，加入失败时后续部分被释放，可恢复任务不能是串行任务，
+* 也不能加入后端为工作窃取任务池的全局任务池
+*/
+pub fn push_resumable(sync: &Arc<(Mutex<TaskPool>, Condvar)>, task_type: TaskType, priority: u64, func: Box<FnBox() -> Resume + Send>, info: Atom) -> GenResult<usize, String> {
+    push_resumable_with(sync, task_type, priority, func, info, Vec::new(), false)
+}
+
+/*
+* 线程安全的向指定任务池加入一个带有指定标签和是否可丢弃的可恢复任务，返回第一段任务的唯一id，后续部分保留相同的标签、是否可丢弃和上下文
+*/
+pub fn push_resumable_with(sync: &Arc<(Mutex<TaskPool>, Condvar)>, task_type: TaskType, priority: u64, func: Box<FnBox() -> Resume + Send>, info: Atom, tags: Vec<Atom>, sheddable: bool) -> GenResult<usize, String> {
+    if is_steal_backend(sync) {
+        return Err(format!("push resumable task failed, steal pool not support, info: {}", *info));
+    }
+
+    let resume = ResumeInfo {
+        sync:       sync.clone(),
+        task_type:  task_type,
+        priority:   priority,
+        info:       info,
+        tags:       tags,
+        sheddable:  sheddable,
+        context:    current(),
+    };
+    push_resume(&resume, func)
+}
+
+/*
+* 线程安全的向指定任务池加入一个分时间片执行的任务，返回第一段任务的唯一id，每个时间片调用一次任务函数，任务函数返回true表示已完成，
+* 返回false表示时间片已用完，需要让出工作者，任务函数应在时间片用完后尽快返回
+*/
+pub fn push_sliced<F>(sync: &Arc<(Mutex<TaskPool>, Condvar)>, task_type: TaskType, priority: u64, slice: Duration, func: F, info: Atom) -> GenResult<usize, String>
+    where F: FnMut(&TimeSlice) -> bool + Send + 'static {
+    push_resumable(sync, task_type, priority, sliced(func, slice), info)
+}
+
+//以指定的调度信息和上下文将可恢复任务加入任务池，加入失败时交还的任务函数在任务池锁外释放
+fn push_resume(resume: &ResumeInfo, func: Box<FnBox() -> Resume + Send>) -> GenResult<usize, String> {
+    let task = resumable(resume.clone(), func);
+    let (task_type, priority, info, tags, sheddable) = (resume.task_type, resume.priority, resume.info.clone(), resume.tags.clone(), resume.sheddable);
+    run_with(resume.context.clone(), || {
+        push_task(&resume.sync, task_type, move |task_pool| {
+            task_pool.push_with(task_type, priority, task, info, tags, sheddable)
+        })
+    }).map_err(String::from)
+}
+
+//构建执行可恢复任务的任务函数，任务让出时将后续部分重新加入任务池
+fn resumable(resume: ResumeInfo, func: Box<FnBox() -> Resume + Send>) -> Box<FnBox() + Send> {
+    Box::new(move || {
+        if let Resume::Yield(next) = func() {
+            if let Err(e) = push_resume(&resume, next) {
+                println!("!!!> Requeue Resumable Task Error, info: {}, e: {}", *resume.info, e);
+            }
+        }
+    })
+}
+
+//构建分时间片执行的可恢复任务函数
+fn sliced<F>(mut func: F, slice: Duration) -> Box<FnBox() -> Resume + Send>
+    where F: FnMut(&TimeSlice) -> bool + Send + 'static {
+    Box::new(move || {
+        if func(&TimeSlice::new(slice)) {
+            Resume::Done
+        } else {
+            Resume::Yield(sliced(func, slice))
+        }
+    })
+}
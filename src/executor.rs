@@ -0,0 +1,137 @@
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use std::sync::{Arc, Mutex, Condvar};
+use std::result::Result as GenResult;
+use std::fmt::{Display, Formatter, Result};
+
+use task::Task;
+use task_pool::{TaskPool, AgingConfig};
+use policy::PolicyRng;
+
+/*
+* 测试执行器，在当前线程中按需执行指定任务池的任务，调度随机数使用指定的种子，并关闭任务老化，使相同的任务产生相同的执行顺序，
+* 任务池使用手动时钟，只有推进时钟后定时任务才会到达执行时间，已有工作者在任务池上工作时不能安装测试执行器，
+* 任务发生异常时先完成任务，再将异常抛出给调用者，释放时恢复任务池的老化配置、调度随机数生成器和时钟
+*/
+pub struct TestExecutor {
+    sync:       Arc<(Mutex<TaskPool>, Condvar)>,    //执行的任务池
+    aging:      Option<AgingConfig>,                //安装前的任务老化配置
+    rng:        Option<PolicyRng>,                  //安装前的调度随机数生成器
+    clock:      bool,                               //安装前是否使用手动时钟
+    counter:    usize,                              //已执行的任务数量
+}
+
+impl Display for TestExecutor {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "TestExecutor[aging = {:?}, clock = {}, counter = {}]", self.aging, self.clock, self.counter)
+	}
+}
+
+impl Drop for TestExecutor {
+    fn drop(&mut self) {
+        let &(ref lock, _) = &*self.sync;
+        if let Ok(mut task_pool) = lock.lock() {
+            (*task_pool).set_aging(self.aging);
+            (*task_pool).set_manual_clock(self.clock);
+            if let Some(rng) = self.rng.take() {
+                (*task_pool).replace_rng(rng);
+            }
+        }
+    }
+}
+
+impl TestExecutor {
+    //构建一个执行指定任务池的测试执行器，并设置任务池的调度随机数种子，已有工作者在任务池上工作则返回错误
+    pub fn new(sync: Arc<(Mutex<TaskPool>, Condvar)>, seed: u64) -> GenResult<Self, String> {
+        let (aging, clock, rng) = {
+            let &(ref lock, _) = &*sync;
+            let mut task_pool = lock.lock().unwrap();
+            if (*task_pool).worker_size() > 0 {
+                return Err(format!("install test executor failed, workers attached, worker_size: {}", (*task_pool).worker_size()));
+            }
+
+            let aging = (*task_pool).get_aging();
+            (*task_pool).set_aging(None);
+            let clock = (*task_pool).is_manual_clock();
+            (*task_pool).set_manual_clock(true);
+            (aging, clock, (*task_pool).replace_rng(PolicyRng::with_seed(seed)))
+        };
+        Ok(TestExecutor {
+            sync:       sync,
+            aging:      aging,
+            rng:        Some(rng),
+            clock:      clock,
+            counter:    0,
+        })
+    }
+
+    //获取执行的任务池
+    pub fn get_pool(&self) -> &Arc<(Mutex<TaskPool>, Condvar)> {
+        &self.sync
+    }
+
+    //获取已执行的任务数量
+    pub fn count(&self) -> usize {
+        self.counter
+    }
+
+    //推进任务池的手动时钟，不执行任务，已到执行时间的定时任务在下次执行时加入任务队列
+    pub fn advance(&mut self, duration: Duration) {
+        let &(ref lock, _) = &*self.sync;
+        lock.lock().unwrap().advance_clock(duration);
+    }
+
+    //弹出并执行一个任务，包括已到执行时间的定时任务，没有可执行的任务返回false
+    pub fn step(&mut self) -> bool {
+        let &(ref lock, ref cvar) = &*self.sync;
+        let mut task = Task::new();
+        let (popped, released) = {
+            let mut task_pool = lock.lock().unwrap();
+            (*task_pool).poll_timed();
+            let popped = (*task_pool).pop(&mut task);
+            (popped, (*task_pool).take_released())
+        };
+        drop(released); //在任务池锁外释放被丢弃的任务的任务函数
+        if !popped {
+            return false;
+        }
+
+        //在任务池锁外执行任务，任务中可以继续向任务池加入任务
+        let observer = task.get_observer().cloned();
+        if let Some(ref o) = observer {
+            o.get_observer().on_start(&o.event(&task, None));
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(|| { task.run(); }));
+        if let Some(ref o) = observer {
+            match result {
+                Ok(_) => o.get_observer().on_finish(&o.event(&task, None)),
+                Err(_) => o.get_observer().on_panic(&o.event(&task, None)),
+            }
+        }
+        self.counter += 1;
+
+        {
+            let mut task_pool = lock.lock().unwrap();
+            (*task_pool).complete(&mut task);
+            if (*task_pool).blocked_size() > 0 || (*task_pool).is_idle() {
+                //唤醒等待任务池空间或等待任务池空闲的调用者
+                cvar.notify_all();
+            }
+        }
+        if let Err(e) = result {
+            //任务已完成，串行任务和独占任务可以继续执行，在任务池锁外将异常抛出给调用者
+            panic::resume_unwind(e);
+        }
+        true
+    }
+
+    //执行任务直到没有可执行的任务，包括执行中加入的任务，未到执行时间的定时任务不会等待，返回执行的任务数量
+    pub fn run_until_idle(&mut self) -> usize {
+        let mut count = 0;
+        while self.step() {
+            count += 1;
+        }
+        count
+    }
+}
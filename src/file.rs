@@ -119,13 +119,13 @@ pub trait Shared {
     fn new(file: Self::T) -> Arc<Self::T>;
 
     //原子的从指定位置开始读指定字节
-    fn pread(self, pos: u64, len: usize, callback: Box<FnBox(Arc<Self::T>, Result<Vec<u8>>)>);
+    fn pread(self, pos: u64, len: usize, callback: Box<FnBox(Arc<Self::T>, Result<Vec<u8>>) + Send>);
 
     //原子的从指定位置开始读指定字节，并填充指定的向量
-    fn fpread(self, buf: Vec<u8>, buf_pos: u64, pos: u64, len: usize, callback: Box<FnBox(Arc<Self::T>, Result<Vec<u8>>)>);
+    fn fpread(self, buf: Vec<u8>, buf_pos: u64, pos: u64, len: usize, callback: Box<FnBox(Arc<Self::T>, Result<Vec<u8>>) + Send>);
 
     //原子的从指定位置开始写指定字节
-    fn pwrite(self, options: WriteOptions, pos: u64, bytes: Vec<u8>, callback: Box<FnBox(Arc<Self::T>, Result<usize>)>);
+    fn pwrite(self, options: WriteOptions, pos: u64, bytes: Vec<u8>, callback: Box<FnBox(Arc<Self::T>, Result<usize>) + Send>);
 }
 
 /*
@@ -140,7 +140,7 @@ impl Shared for SharedFile {
         Arc::new(file)
     }
 
-    fn pread(self, pos: u64, len: usize, callback: Box<FnBox(Arc<Self::T>, Result<Vec<u8>>)>) {
+    fn pread(self, pos: u64, len: usize, callback: Box<FnBox(Arc<Self::T>, Result<Vec<u8>>) + Send>) {
         if len == 0 {
             return callback(self, Err(Error::new(ErrorKind::Other, "pread failed, invalid len")));
         }
@@ -150,7 +150,7 @@ impl Shared for SharedFile {
         pread_continue(vec, 0, self, pos, len, callback);
     }
 
-    fn fpread(self, buf: Vec<u8>, buf_pos: u64, pos: u64, len: usize, callback: Box<FnBox(Arc<Self::T>, Result<Vec<u8>>)>) {
+    fn fpread(self, buf: Vec<u8>, buf_pos: u64, pos: u64, len: usize, callback: Box<FnBox(Arc<Self::T>, Result<Vec<u8>>) + Send>) {
         if len == 0 {
             return callback(self, Err(Error::new(ErrorKind::Other, "fpread failed, invalid len")));
         }
@@ -170,7 +170,7 @@ impl Shared for SharedFile {
         fpread_continue(vec, buf_pos, self, pos, len, callback);
     }
 
-    fn pwrite(self, options: WriteOptions, pos: u64, bytes: Vec<u8>, callback: Box<FnBox(Arc<Self::T>, Result<usize>)>) {
+    fn pwrite(self, options: WriteOptions, pos: u64, bytes: Vec<u8>, callback: Box<FnBox(Arc<Self::T>, Result<usize>) + Send>) {
         let len = bytes.len();
         if len == 0 {
             return callback(self, Ok(0));
@@ -215,8 +215,12 @@ impl Clone for AsyncFile {
 
 impl AsyncFile {
     //以指定方式打开指定文件
-    pub fn open<P: AsRef<Path> + Send + 'static>(path: P, options: AsynFileOptions, callback: Box<FnBox(Result<Self>)>) {
-        let func = move || {
+    pub fn open<P: AsRef<Path> + Send + 'static>(path: P, options: AsynFileOptions, callback: Box<FnBox(Result<Self>) + Send>) {
+        let func = move |result: Result<()>| {
+            if let Err(e) = result {
+                return callback(Err(e));
+            }
+
             let (r, w, a, c, t, len) = match options {
                 AsynFileOptions::OnlyRead(len) => (true, false, false, false, false, len),
                 AsynFileOptions::OnlyWrite(len) => (false, true, false, true, false, len),
@@ -248,25 +252,25 @@ impl AsyncFile {
                 },
             }
         };
-        cast_store_task(ASYNC_FILE_TASK_TYPE, OPEN_ASYNC_FILE_PRIORITY, Box::new(func), Atom::from(OPEN_ASYNC_FILE_INFO));
+        cast_file_task(OPEN_ASYNC_FILE_PRIORITY, func, OPEN_ASYNC_FILE_INFO);
     }
 
     //文件重命名
-    pub fn rename<P: AsRef<Path> + Clone + Send + 'static>(from: P, to: P, callback: Box<FnBox(P, P, Result<()>)>) {
-        let func = move || {
-            let result = rename(from.clone(), to.clone());
+    pub fn rename<P: AsRef<Path> + Clone + Send + 'static>(from: P, to: P, callback: Box<FnBox(P, P, Result<()>) + Send>) {
+        let func = move |result: Result<()>| {
+            let result = result.and_then(|_| rename(from.clone(), to.clone()));
             callback(from, to, result);
         };
-        cast_store_task(ASYNC_FILE_TASK_TYPE, RENAME_ASYNC_FILE_PRIORITY, Box::new(func), Atom::from(RENAME_ASYNC_FILE_INFO));
+        cast_file_task(RENAME_ASYNC_FILE_PRIORITY, func, RENAME_ASYNC_FILE_INFO);
     }
 
     //移除指定文件
-    pub fn remove<P: AsRef<Path> + Send + 'static>(path: P, callback: Box<FnBox(Result<()>)>) {
-        let func = move || {
-            let result = remove_file(path);
+    pub fn remove<P: AsRef<Path> + Send + 'static>(path: P, callback: Box<FnBox(Result<()>) + Send>) {
+        let func = move |result: Result<()>| {
+            let result = result.and_then(|_| remove_file(path));
             callback(result);
         };
-        cast_store_task(ASYNC_FILE_TASK_TYPE, REMOVE_ASYNC_FILE_PRIORITY, Box::new(func), Atom::from(REMOVE_ASYNC_FILE_INFO));
+        cast_file_task(REMOVE_ASYNC_FILE_PRIORITY, func, REMOVE_ASYNC_FILE_INFO);
     }
 
     //检查是否是符号链接
@@ -329,8 +333,12 @@ impl AsyncFile {
     }
 
     //从指定位置开始，读指定字节
-    pub fn read(mut self, pos: u64, len: usize, callback: Box<FnBox(Self, Result<Vec<u8>>)>) {
-        let func = move || {
+    pub fn read(mut self, pos: u64, len: usize, callback: Box<FnBox(Self, Result<Vec<u8>>) + Send>) {
+        let func = move |result: Result<()>| {
+            if let Err(e) = result {
+                return callback(init_read_file(self), Err(e));
+            }
+
             let file_size = self.get_size();
             if file_size == 0 || len == 0 {
                 let vec = self.buffer.take().unwrap();
@@ -387,12 +395,16 @@ impl AsyncFile {
                 },
             }
         };
-        cast_store_task(ASYNC_FILE_TASK_TYPE, READ_ASYNC_FILE_PRIORITY, Box::new(func), Atom::from(READ_ASYNC_FILE_INFO));
+        cast_file_task(READ_ASYNC_FILE_PRIORITY, func, READ_ASYNC_FILE_INFO);
     }
 
     //从指定位置开始，写指定字节
-    pub fn write(mut self, options: WriteOptions, pos: u64, bytes: Vec<u8>, callback: Box<FnBox(Self, Result<()>)>) {
-        let func = move || {
+    pub fn write(mut self, options: WriteOptions, pos: u64, bytes: Vec<u8>, callback: Box<FnBox(Self, Result<()>) + Send>) {
+        let func = move |result: Result<()>| {
+            if let Err(e) = result {
+                return callback(init_write_file(self), Err(e));
+            }
+
             if !&bytes[self.pos as usize..].is_empty() {
                 match self.inner.seek(SeekFrom::Start(pos as u64)) {
                     Err(e) => callback(init_write_file(self), Err(e)),
@@ -429,7 +441,7 @@ impl AsyncFile {
                 callback(init_write_file(self), result);
             }
         };
-        cast_store_task(ASYNC_FILE_TASK_TYPE, WRITE_ASYNC_FILE_PRIORITY, Box::new(func), Atom::from(WRITE_ASYNC_FILE_INFO));
+        cast_file_task(WRITE_ASYNC_FILE_PRIORITY, func, WRITE_ASYNC_FILE_INFO);
     }
 }
 
@@ -460,6 +472,38 @@ fn alloc_buffer(mut file: AsyncFile, file_size: u64, len: usize) -> AsyncFile {
     file
 }
 
+/*
+* 文件异步访问任务，任务未执行就被释放时，包括投递失败和被任务池清空、移除或丢弃，以错误调用任务函数，由任务函数回调调用者
+*/
+struct FileTask<F: FnOnce(Result<()>)> {
+    func:   Option<F>,      //任务函数，执行后为None
+    info:   &'static str,   //任务信息
+}
+
+impl<F: FnOnce(Result<()>)> Drop for FileTask<F> {
+    fn drop(&mut self) {
+        if let Some(func) = self.func.take() {
+            func(Err(Error::new(ErrorKind::Other, format!("file task dropped without running, info: {}", self.info))));
+        }
+    }
+}
+
+//向存储任务池投递文件异步访问任务，任务函数的参数为Ok表示在存储任务池中执行，任务未执行就被释放则以错误调用任务函数，由任务函数回调调用者，
+//任务池总是在锁外释放任务，所以可以在释放时回调
+fn cast_file_task<F>(priority: u64, func: F, info: &'static str)
+    where F: FnOnce(Result<()>) + Send + 'static {
+    let mut file_task = FileTask {
+        func: Some(func),
+        info: info,
+    };
+    let task = Box::new(move || {
+        if let Some(func) = file_task.func.take() {
+            func(Ok(()));
+        }
+    });
+    let _ = cast_store_task(ASYNC_FILE_TASK_TYPE, priority, task, Atom::from(info)); //投递失败时交还的任务在此释放，并以错误调用任务函数
+}
+
 #[cfg(any(unix))]
 fn get_block_size(meta: &Metadata) -> usize {
     use std::os::unix::fs::MetadataExt;
@@ -472,8 +516,12 @@ fn get_block_size(_meta: &Metadata) -> usize {
 }
 
 //继续读
-fn pread_continue(mut vec: Vec<u8>, vec_pos: u64, file: SharedFile, pos: u64, len: usize, callback: Box<FnBox(Arc<<SharedFile as Shared>::T>, Result<Vec<u8>>)>) {
-    let func = move || {
+fn pread_continue(mut vec: Vec<u8>, vec_pos: u64, file: SharedFile, pos: u64, len: usize, callback: Box<FnBox(Arc<<SharedFile as Shared>::T>, Result<Vec<u8>>) + Send>) {
+    let func = move |result: Result<()>| {
+        if let Err(e) = result {
+            return callback(file, Err(e));
+        }
+
         #[cfg(any(unix))]
         let r = file.inner.read_at(&mut vec[vec_pos as usize..(vec_pos as usize + len)], pos);
         #[cfg(any(windows))]
@@ -499,12 +547,16 @@ fn pread_continue(mut vec: Vec<u8>, vec_pos: u64, file: SharedFile, pos: u64, le
             Err(e) => callback(file, Err(e)),
         }
     };
-    cast_store_task(ASYNC_FILE_TASK_TYPE, READ_ASYNC_FILE_PRIORITY, Box::new(func), Atom::from(SHARED_READ_ASYNC_FILE_INFO));
+    cast_file_task(READ_ASYNC_FILE_PRIORITY, func, SHARED_READ_ASYNC_FILE_INFO);
 }
 
 //继续填充读
-fn fpread_continue(mut vec: Vec<u8>, vec_pos: u64, file: SharedFile, pos: u64, len: usize, callback: Box<FnBox(Arc<<SharedFile as Shared>::T>, Result<Vec<u8>>)>) {
-    let func = move || {
+fn fpread_continue(mut vec: Vec<u8>, vec_pos: u64, file: SharedFile, pos: u64, len: usize, callback: Box<FnBox(Arc<<SharedFile as Shared>::T>, Result<Vec<u8>>) + Send>) {
+    let func = move |result: Result<()>| {
+        if let Err(e) = result {
+            return callback(file, Err(e));
+        }
+
         #[cfg(any(unix))]
         let r = file.inner.read_at(&mut vec[vec_pos as usize..(vec_pos as usize + len)], pos);
         #[cfg(any(windows))]
@@ -530,12 +582,16 @@ fn fpread_continue(mut vec: Vec<u8>, vec_pos: u64, file: SharedFile, pos: u64, l
             Err(e) => callback(file, Err(e)),
         }
     };
-    cast_store_task(ASYNC_FILE_TASK_TYPE, READ_ASYNC_FILE_PRIORITY, Box::new(func), Atom::from(SHARED_READ_ASYNC_FILE_INFO));
+    cast_file_task(READ_ASYNC_FILE_PRIORITY, func, SHARED_READ_ASYNC_FILE_INFO);
 }
 
 //继续写
-fn pwrite_continue(len: usize, mut file: SharedFile, options: WriteOptions, pos: u64, bytes: Vec<u8>, vec_pos: u64, callback: Box<FnBox(Arc<<SharedFile as Shared>::T>, Result<usize>)>) {
-    let func = move || {
+fn pwrite_continue(len: usize, mut file: SharedFile, options: WriteOptions, pos: u64, bytes: Vec<u8>, vec_pos: u64, callback: Box<FnBox(Arc<<SharedFile as Shared>::T>, Result<usize>) + Send>) {
+    let func = move |result: Result<()>| {
+        if let Err(e) = result {
+            return callback(file, Err(e));
+        }
+
         #[cfg(any(unix))]
         let r = file.inner.write_at(&bytes[vec_pos as usize..len], pos);
         #[cfg(any(windows))]
@@ -565,5 +621,5 @@ fn pwrite_continue(len: usize, mut file: SharedFile, options: WriteOptions, pos:
             Err(e) => callback(file, Err(e)),
         }
     };
-    cast_store_task(ASYNC_FILE_TASK_TYPE, WRITE_ASYNC_FILE_PRIORITY, Box::new(func), Atom::from(SHARED_WRITE_ASYNC_FILE_INFO));
+    cast_file_task(WRITE_ASYNC_FILE_PRIORITY, func, SHARED_WRITE_ASYNC_FILE_INFO);
 }
\ No newline at end of file
@@ -9,14 +9,15 @@ use pi_lib::atom::Atom;
 
 use task::TaskType;
 use future::FutTask;
+use context::{current, run_with};
 
 /*
 * 未来任务池
 */
 #[derive(Debug)]
 pub struct FutTaskPool {
-    counter:    AtomicUsize,                                //未来任务计数器
-    executor:   fn(TaskType, u64, Box<FnBox()>, Atom),      //未来任务执行器
+    counter:    AtomicUsize,                                                        //未来任务计数器
+    executor:   fn(TaskType, u64, Box<FnBox() + Send>, Atom) -> Result<usize, String>,     //未来任务执行器
 }
 
 impl Clone for FutTaskPool {
@@ -30,7 +31,7 @@ impl Clone for FutTaskPool {
 
 impl FutTaskPool {
     //构建一个未来任务池
-    pub fn new(executor: fn(TaskType, u64, Box<FnBox()>, Atom)) -> Self {
+    pub fn new(executor: fn(TaskType, u64, Box<FnBox() + Send>, Atom) -> Result<usize, String>) -> Self {
         FutTaskPool {
             counter: AtomicUsize::new(0),
             executor: executor,
@@ -42,17 +43,18 @@ impl FutTaskPool {
         self.counter.load(Ordering::Relaxed)
     }
 
-    //分派一个未来任务
-    pub fn spawn<T, E>(&self, callback: Box<FnBox(fn(TaskType, u64, Box<FnBox()>, Atom), Arc<Producer<Result<T, E>>>, Arc<Consumer<Task>>, usize)>, 
-        timeout: u32) -> FutTask<T, E> where T: Send + 'static, E: Send + 'static {
+    //分派一个未来任务，执行器拒绝任务时返回错误，未来任务不会被完成
+    pub fn spawn<T, E>(&self, callback: Box<FnBox(fn(TaskType, u64, Box<FnBox() + Send>, Atom) -> Result<usize, String>, Arc<Producer<Result<T, E>>>, Arc<Consumer<Task>>, usize) + Send>, 
+        timeout: u32) -> Result<FutTask<T, E>, String> where T: Send + 'static, E: Send + 'static {
             let uid = self.counter.fetch_add(1, Ordering::SeqCst);
             let (p0, c0) = npnc_channel(1);
             let (p1, c1) = npnc_channel(1);
             let copy = self.executor;
+            let context = current(); //执行器不一定会捕获上下文，所以在分派时捕获
             let func = Box::new(move || {
-                callback(copy, Arc::new(p0), Arc::new(c1), uid);
+                run_with(context, move || callback(copy, Arc::new(p0), Arc::new(c1), uid));
             });
-            (self.executor)(TaskType::Sync, 10000000, func, Atom::from(uid.to_string() + " future task"));
-            FutTask::new(uid, timeout, Arc::new(c0), Arc::new(p1))
+            (self.executor)(TaskType::Sync, 10000000, func, Atom::from(uid.to_string() + " future task"))?;
+            Ok(FutTask::new(uid, timeout, Arc::new(c0), Arc::new(p1)))
     }
 }
\ No newline at end of file
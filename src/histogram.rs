@@ -0,0 +1,119 @@
+use std::time::Duration;
+use std::fmt::{Display, Formatter, Result};
+
+/*
+* 直方图的桶数量，第0个桶记录0us，第i个桶记录[2^(i-1), 2^i)us
+*/
+const HISTOGRAM_BUCKET_SIZE: usize = 65;
+
+/*
+* 时长直方图，按2的幂次划分桶，单位us，记录和查询都是O(1)的，分位数的误差不超过一倍
+*/
+#[derive(Clone)]
+pub struct Histogram {
+    buckets:    Vec<u64>,   //各桶的记录数量
+    count:      u64,        //记录数量
+    sum:        u64,        //记录的时长总和，单位us
+    max:        u64,        //记录的最大时长，单位us
+}
+
+impl Display for Histogram {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "Histogram[count = {}, mean = {:?}, p50 = {:?}, p99 = {:?}, max = {:?}]",
+            self.count, self.mean(), self.percentile(0.5), self.percentile(0.99), self.max())
+	}
+}
+
+impl Histogram {
+    //构建一个时长直方图
+    pub fn new() -> Self {
+        Histogram {
+            buckets:    vec![0; HISTOGRAM_BUCKET_SIZE],
+            count:      0,
+            sum:        0,
+            max:        0,
+        }
+    }
+
+    //记录一个时长
+    pub fn record(&mut self, duration: Duration) {
+        let micros = micros(duration);
+        self.buckets[bucket_index(micros)] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(micros);
+        if micros > self.max {
+            self.max = micros;
+        }
+    }
+
+    //获取记录数量
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    //获取平均时长
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::new(0, 0);
+        }
+        Duration::from_micros(self.sum / self.count)
+    }
+
+    //获取最大时长
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max)
+    }
+
+    //获取指定分位数的时长，分位数在0到1之间，返回分位数所在桶的上界，不超过最大时长，没有记录返回0
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::new(0, 0);
+        }
+
+        let p = if p < 0.0 { 0.0 } else if p > 1.0 { 1.0 } else { p };
+        let rank = ((self.count as f64) * p).ceil() as u64;
+        let rank = if rank == 0 { 1 } else { rank };
+        let mut total = 0;
+        for index in 0..self.buckets.len() {
+            total += self.buckets[index];
+            if total >= rank {
+                let upper = bucket_upper(index);
+                return Duration::from_micros(if upper > self.max { self.max } else { upper });
+            }
+        }
+        Duration::from_micros(self.max)
+    }
+
+    //清空所有记录
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            *bucket = 0;
+        }
+        self.count = 0;
+        self.sum = 0;
+        self.max = 0;
+    }
+}
+
+//获取指定时长所在桶的位置
+#[inline]
+fn bucket_index(micros: u64) -> usize {
+    64 - micros.leading_zeros() as usize
+}
+
+//获取指定桶的上界，单位us
+#[inline]
+fn bucket_upper(index: usize) -> u64 {
+    if index == 0 {
+        0
+    } else if index >= 64 {
+        u64::max_value()
+    } else {
+        (1 << index) - 1
+    }
+}
+
+//获取时长的微秒数
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs().saturating_mul(1000000).saturating_add(duration.subsec_micros() as u64)
+}
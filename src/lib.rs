@@ -19,6 +19,7 @@ extern crate libc;
 extern crate npnc;
 extern crate futures;
 extern crate threadpool;
+extern crate crossbeam_deque;
 
 #[macro_use]
 extern crate lazy_static;
@@ -37,4 +38,18 @@ pub mod worker;
 pub mod worker_pool;
 pub mod task;
 pub mod task_pool;
+pub mod policy;
+pub mod weight_tree;
+pub mod steal_pool;
+pub mod task_group;
+pub mod task_graph;
+pub mod task_handle;
+pub mod observer;
+pub mod histogram;
+pub mod trace;
+pub mod executor;
+pub mod scope;
+pub mod par;
+pub mod continuation;
+pub mod context;
 pub mod util;
\ No newline at end of file
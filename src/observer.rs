@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::time::Instant;
+use std::fmt::{Debug, Formatter, Result};
+
+use pi_lib::atom::Atom;
+use task::{TaskType, Task};
+use context::TaskContext;
+
+/*
+* 任务生命周期事件
+*/
+#[derive(Debug, Clone)]
+pub struct TaskEvent {
+    pub pool:       Atom,               //任务池名
+    pub uid:        usize,              //任务唯一id
+    pub info:       Atom,               //任务信息
+    pub priority:   u64,                //任务优先级
+    pub task_type:  TaskType,           //任务类型
+    pub worker:     Option<u32>,        //执行任务的工作者编号，只有开始、结束和异常事件有
+    pub queued:     Option<Instant>,    //任务进入就绪队列的时间，未进入就绪队列为None
+    pub time:       Instant,            //事件发生的时间
+    pub context:    Option<TaskContext>,//任务上下文
+}
+
+/*
+* 任务生命周期观察者，加入、弹出和取消事件在持有任务池锁时调用，不允许在回调中访问任务池，
+* 开始、结束和异常事件在工作者线程中调用，所有回调都应尽快返回
+*/
+pub trait TaskObserver: Send + Sync + 'static {
+    //任务加入任务池
+    fn on_push(&self, _event: &TaskEvent) {}
+
+    //任务从任务池弹出，即将分派给工作者
+    fn on_pop(&self, _event: &TaskEvent) {}
+
+    //工作者开始执行任务
+    fn on_start(&self, _event: &TaskEvent) {}
+
+    //工作者执行任务完成
+    fn on_finish(&self, _event: &TaskEvent) {}
+
+    //工作者执行任务时发生异常
+    fn on_panic(&self, _event: &TaskEvent) {}
+
+    //任务被取消、驱逐或移除，不会再被执行
+    fn on_cancel(&self, _event: &TaskEvent) {}
+}
+
+/*
+* 任务池的观察者，任务池弹出任务时会将其复制到任务中，供工作者使用
+*/
+#[derive(Clone)]
+pub struct PoolObserver {
+    name:       Atom,               //任务池名
+    observer:   Arc<TaskObserver>,  //观察者
+}
+
+impl Debug for PoolObserver {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "PoolObserver[name = {}]", *self.name)
+	}
+}
+
+impl PoolObserver {
+    //构建一个指定任务池名的观察者
+    pub fn new(name: Atom, observer: Arc<TaskObserver>) -> Self {
+        PoolObserver {
+            name:       name,
+            observer:   observer,
+        }
+    }
+
+    //获取任务池名
+    pub fn get_name(&self) -> &Atom {
+        &self.name
+    }
+
+    //获取观察者
+    pub fn get_observer(&self) -> &Arc<TaskObserver> {
+        &self.observer
+    }
+
+    //构建指定任务的事件
+    pub fn event<T>(&self, task: &Task<T>, worker: Option<u32>) -> TaskEvent {
+        TaskEvent {
+            pool:       self.name.clone(),
+            uid:        task.get_uid(),
+            info:       task.get_info_atom().clone(),
+            priority:   task.get_priority(),
+            task_type:  task.get_type(),
+            worker:     worker,
+            queued:     task.get_time(),
+            time:       Instant::now(),
+            context:    task.get_context().cloned(),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::any::Any;
+use std::sync::{Arc, Mutex, Condvar};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{Ordering, AtomicBool, AtomicUsize};
+
+use pi_lib::atom::Atom;
+use task::TaskType;
+use task_pool::TaskPool;
+use scope::scope;
+
+/*
+* 并行迭代任务的类型和优先级
+*/
+const PAR_TASK_TYPE: TaskType = TaskType::Async;
+const PAR_TASK_PRIORITY: u64 = 10;
+
+/*
+* 并行迭代任务信息
+*/
+const PAR_TASK_INFO: &'static str = "par task";
+
+/*
+* 并行迭代加入任务池的任务数量，调用者线程也会参与迭代
+*/
+const PAR_TASK_SIZE: usize = 8;
+
+/*
+* 并行迭代的分块系数，每次领取剩余数量除以任务数量和分块系数的块，越往后块越小，执行快的任务会领取更多的块
+*/
+const PAR_SPLIT_FACTOR: usize = 2;
+
+/*
+* 线程安全的在指定任务池中并行处理所有元素，元素被分块后由任务池的工作者和调用者共同处理，有块发生异常时，其它块不再继续处理，
+* 等待已开始的块结束后将异常抛出给调用者
+*/
+pub fn par_for_each<T, F>(sync: &Arc<(Mutex<TaskPool>, Condvar)>, items: &[T], f: F)
+    where T: Sync, F: Fn(&T) + Sync {
+    par_chunks(sync, items.len(), |start, end| {
+        for item in items[start..end].iter() {
+            f(item);
+        }
+    });
+}
+
+/*
+* 线程安全的在指定任务池中并行映射所有元素，按元素顺序返回结果，有块发生异常时将异常抛出给调用者
+*/
+pub fn par_map<T, R, F>(sync: &Arc<(Mutex<TaskPool>, Condvar)>, items: &[T], f: F) -> Vec<R>
+    where T: Sync, R: Send, F: Fn(&T) -> R + Sync {
+    let results: Mutex<Vec<(usize, Vec<R>)>> = Mutex::new(Vec::new());
+    par_chunks(sync, items.len(), |start, end| {
+        let chunk: Vec<R> = items[start..end].iter().map(|item| f(item)).collect();
+        results.lock().unwrap().push((start, chunk));
+    });
+
+    //按块的位置合并结果
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|&(start, _)| start);
+    let mut vec = Vec::with_capacity(items.len());
+    for (_, chunk) in results {
+        vec.extend(chunk);
+    }
+    vec
+}
+
+/*
+* 线程安全的在指定任务池中并行映射并归约所有元素，归约函数需要满足结合律，块内和块间都按元素顺序归约，没有元素返回None，
+* 有块发生异常时将异常抛出给调用者
+*/
+pub fn par_reduce<T, R, M, F>(sync: &Arc<(Mutex<TaskPool>, Condvar)>, items: &[T], map: M, reduce: F) -> Option<R>
+    where T: Sync, R: Send, M: Fn(&T) -> R + Sync, F: Fn(R, R) -> R + Sync {
+    let results: Mutex<Vec<(usize, R)>> = Mutex::new(Vec::new());
+    par_chunks(sync, items.len(), |start, end| {
+        let mut iter = items[start..end].iter().map(|item| map(item));
+        if let Some(first) = iter.next() {
+            let r = iter.fold(first, |acc, r| reduce(acc, r));
+            results.lock().unwrap().push((start, r));
+        }
+    });
+
+    //按块的位置归约结果
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|&(start, _)| start);
+    let mut iter = results.into_iter().map(|(_, r)| r);
+    match iter.next() {
+        None => None,
+        Some(first) => Some(iter.fold(first, |acc, r| reduce(acc, r))),
+    }
+}
+
+//将[0, len)自适应分块，由任务池的工作者和调用者并行处理，有块发生异常时抛出给调用者
+fn par_chunks<F>(sync: &Arc<(Mutex<TaskPool>, Condvar)>, len: usize, f: F)
+    where F: Fn(usize, usize) + Sync {
+    if len == 0 {
+        return;
+    }
+
+    let next = AtomicUsize::new(0);
+    let abort = AtomicBool::new(false);
+    let error: Mutex<Option<Box<Any + Send>>> = Mutex::new(None);
+    let size = if len < PAR_TASK_SIZE { len } else { PAR_TASK_SIZE };
+    let work = || {
+        while !abort.load(Ordering::SeqCst) {
+            let (start, end) = match claim(&next, len, size) {
+                None => break,
+                Some(r) => r,
+            };
+            if let Err(e) = panic::catch_unwind(AssertUnwindSafe(|| f(start, end))) {
+                abort.store(true, Ordering::SeqCst);
+                let mut error = error.lock().unwrap();
+                if error.is_none() {
+                    *error = Some(e);
+                }
+            }
+        }
+    };
+
+    let _ = scope(sync, |s| {
+        let work = &work;
+        for _ in 1..size {
+            //加入失败时由调用者处理剩余的块
+            let _ = s.spawn(PAR_TASK_TYPE, PAR_TASK_PRIORITY, move || work(), Atom::from(PAR_TASK_INFO));
+        }
+        work();
+    });
+
+    if let Some(e) = error.into_inner().unwrap() {
+        panic::resume_unwind(e);
+    }
+}
+
+//领取下一个块，越往后块越小，已全部领取返回None
+fn claim(next: &AtomicUsize, len: usize, size: usize) -> Option<(usize, usize)> {
+    let mut start = next.load(Ordering::SeqCst);
+    loop {
+        if start >= len {
+            return None;
+        }
+
+        let chunk = (len - start) / (size * PAR_SPLIT_FACTOR);
+        let end = start + if chunk == 0 { 1 } else { chunk };
+        match next.compare_exchange(start, end, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => return Some((start, end)),
+            Err(current) => start = current,
+        }
+    }
+}
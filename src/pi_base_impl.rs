@@ -1,10 +1,23 @@
 use std::boxed::FnBox;
+use std::time::Instant;
 use std::sync::{Arc, Mutex, Condvar};
+use std::sync::atomic::{Ordering, AtomicUsize};
 
 use pi_lib::atom::Atom;
 
 use task::TaskType;
-use task_pool::TaskPool;
+use task_pool::{TaskPool, push_task};
+use steal_pool::StealPool;
+use executor::TestExecutor;
+
+/*
+* 任务池后端
+*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PoolBackend {
+    Mutex = 0,  //互斥任务池，支持优先级、串行任务、定时任务和取消任务
+    Steal,      //工作窃取任务池，无锁且工作者不需要轮询，只区分延迟任务，不支持串行任务、定时任务和取消任务
+}
 
 /*
 * 虚拟机任务池
@@ -27,33 +40,273 @@ lazy_static! {
 	pub static ref EXT_TASK_POOL: Arc<(Mutex<TaskPool>, Condvar)> = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
 }
 
+/*
+* 虚拟机工作窃取任务池
+*/
+lazy_static! {
+	pub static ref JS_STEAL_POOL: Arc<StealPool> = Arc::new(StealPool::new());
+}
+
+/*
+* 存储工作窃取任务池
+*/
+lazy_static! {
+	pub static ref STORE_STEAL_POOL: Arc<StealPool> = Arc::new(StealPool::new());
+}
+
+/*
+* 外部工作窃取任务池
+*/
+lazy_static! {
+	pub static ref EXT_STEAL_POOL: Arc<StealPool> = Arc::new(StealPool::new());
+}
+
+/*
+* 各任务池使用的后端，默认为互斥任务池
+*/
+static JS_BACKEND: AtomicUsize = AtomicUsize::new(PoolBackend::Mutex as usize);
+static STORE_BACKEND: AtomicUsize = AtomicUsize::new(PoolBackend::Mutex as usize);
+static EXT_BACKEND: AtomicUsize = AtomicUsize::new(PoolBackend::Mutex as usize);
+
+/*
+* 设置虚拟机任务池的后端，需要在投递任务前设置，并在对应的任务池上运行工作者池，互斥任务池为JS_TASK_POOL，工作窃取任务池为JS_STEAL_POOL
+*/
+pub fn set_js_backend(backend: PoolBackend) {
+    JS_BACKEND.store(backend as usize, Ordering::SeqCst);
+}
+
+/*
+* 设置存储任务池的后端，互斥任务池为STORE_TASK_POOL，工作窃取任务池为STORE_STEAL_POOL
+*/
+pub fn set_store_backend(backend: PoolBackend) {
+    STORE_BACKEND.store(backend as usize, Ordering::SeqCst);
+}
+
+/*
+* 设置外部任务池的后端，互斥任务池为EXT_TASK_POOL，工作窃取任务池为EXT_STEAL_POOL
+*/
+pub fn set_ext_backend(backend: PoolBackend) {
+    EXT_BACKEND.store(backend as usize, Ordering::SeqCst);
+}
+
+/*
+* 获取虚拟机任务池的后端
+*/
+pub fn get_js_backend() -> PoolBackend {
+    backend(&JS_BACKEND)
+}
+
+/*
+* 获取存储任务池的后端
+*/
+pub fn get_store_backend() -> PoolBackend {
+    backend(&STORE_BACKEND)
+}
+
+/*
+* 获取外部任务池的后端
+*/
+pub fn get_ext_backend() -> PoolBackend {
+    backend(&EXT_BACKEND)
+}
+
+/*
+* 为虚拟机任务池安装使用指定随机数种子的测试执行器，任务池后端被设置为互斥任务池，投递的任务由测试执行器在当前线程执行，已有工作者在任务池上工作则返回错误
+*/
+pub fn install_js_executor(seed: u64) -> Result<TestExecutor, String> {
+    let executor = TestExecutor::new(JS_TASK_POOL.clone(), seed)?;
+    set_js_backend(PoolBackend::Mutex);
+    Ok(executor)
+}
 
 /*
-* 线程安全的向虚拟机任务池投递任务
+* 为存储任务池安装使用指定随机数种子的测试执行器
 */
-pub fn cast_js_task(task_type: TaskType, priority: u64, func: Box<FnBox()>, info: Atom) {
-    let &(ref lock, ref cvar) = &**JS_TASK_POOL;
+pub fn install_store_executor(seed: u64) -> Result<TestExecutor, String> {
+    let executor = TestExecutor::new(STORE_TASK_POOL.clone(), seed)?;
+    set_store_backend(PoolBackend::Mutex);
+    Ok(executor)
+}
+
+/*
+* 为外部任务池安装使用指定随机数种子的测试执行器
+*/
+pub fn install_ext_executor(seed: u64) -> Result<TestExecutor, String> {
+    let executor = TestExecutor::new(EXT_TASK_POOL.clone(), seed)?;
+    set_ext_backend(PoolBackend::Mutex);
+    Ok(executor)
+}
+
+/*
+* 线程安全的向虚拟机任务池投递任务，返回任务唯一id，任务池已满则按任务池的处理策略处理
+*/
+pub fn cast_js_task(task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_js_backend() {
+        return JS_STEAL_POOL.push(task_type, priority, func, info);
+    }
+
+    push_task(&JS_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push(task_type, priority, func, info)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的向存储任务池投递任务，返回任务唯一id，任务池已满则按任务池的处理策略处理
+*/
+pub fn cast_store_task(task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_store_backend() {
+        return STORE_STEAL_POOL.push(task_type, priority, func, info);
+    }
+
+    push_task(&STORE_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push(task_type, priority, func, info)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的向外部任务池投递任务，返回任务唯一id，任务池已满则按任务池的处理策略处理
+*/
+pub fn cast_ext_task(task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_ext_backend() {
+        return EXT_STEAL_POOL.push(task_type, priority, func, info);
+    }
+
+    push_task(&EXT_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push(task_type, priority, func, info)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的向虚拟机任务池投递指定键的串行任务，返回任务唯一id，任务池已满则按任务池的处理策略处理
+*/
+pub fn cast_js_serial_task(key: Atom, task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_js_backend() {
+        return Err(format!("cast serial task failed, steal pool not support, key: {}", *key));
+    }
+
+    push_task(&JS_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push_serial(key, task_type, priority, func, info)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的向存储任务池投递指定键的串行任务，返回任务唯一id，任务池已满则按任务池的处理策略处理
+*/
+pub fn cast_store_serial_task(key: Atom, task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_store_backend() {
+        return Err(format!("cast serial task failed, steal pool not support, key: {}", *key));
+    }
+
+    push_task(&STORE_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push_serial(key, task_type, priority, func, info)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的向外部任务池投递指定键的串行任务，返回任务唯一id，任务池已满则按任务池的处理策略处理
+*/
+pub fn cast_ext_serial_task(key: Atom, task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_ext_backend() {
+        return Err(format!("cast serial task failed, steal pool not support, key: {}", *key));
+    }
+
+    push_task(&EXT_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push_serial(key, task_type, priority, func, info)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的向虚拟机任务池投递在指定时间执行的定时任务，返回任务唯一id
+*/
+pub fn cast_js_task_at(task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom, time: Instant) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_js_backend() {
+        return Err(format!("cast timed task failed, steal pool not support, info: {}", *info));
+    }
+
+    push_task(&JS_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push_at(task_type, priority, func, info, time)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的向存储任务池投递在指定时间执行的定时任务，返回任务唯一id
+*/
+pub fn cast_store_task_at(task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom, time: Instant) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_store_backend() {
+        return Err(format!("cast timed task failed, steal pool not support, info: {}", *info));
+    }
+
+    push_task(&STORE_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push_at(task_type, priority, func, info, time)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的向外部任务池投递在指定时间执行的定时任务，返回任务唯一id
+*/
+pub fn cast_ext_task_at(task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom, time: Instant) -> Result<usize, String> {
+    if let PoolBackend::Steal = get_ext_backend() {
+        return Err(format!("cast timed task failed, steal pool not support, info: {}", *info));
+    }
+
+    push_task(&EXT_TASK_POOL, task_type, move |task_pool| {
+        task_pool.push_at(task_type, priority, func, info, time)
+    }).map_err(String::from) //加入失败时交还的任务函数在任务池锁外释放
+}
+
+/*
+* 线程安全的取消虚拟机任务池中未被弹出的任务，成功返回任务函数，任务已被弹出或不存在返回None，后端为工作窃取任务池时不支持取消，返回错误
+*/
+pub fn cancel_js_task(uid: usize) -> Result<Option<Box<FnBox() + Send>>, String> {
+    if let PoolBackend::Steal = get_js_backend() {
+        return Err(format!("cancel task unsupported by steal pool, uid: {}", uid)); //工作窃取任务池不支持取消任务
+    }
+
+    let &(ref lock, _) = &**JS_TASK_POOL;
     let mut task_pool = lock.lock().unwrap();
-    (*task_pool).push(task_type, priority, func, info);
-    cvar.notify_one();
+    Ok((*task_pool).cancel(uid))
 }
 
 /*
-* 线程安全的向存储任务池投递任务
+* 线程安全的取消存储任务池中未被弹出的任务，成功返回任务函数，任务已被弹出或不存在返回None，后端为工作窃取任务池时不支持取消，返回错误
 */
-pub fn cast_store_task(task_type: TaskType, priority: u64, func: Box<FnBox()>, info: Atom) {
-    let &(ref lock, ref cvar) = &**STORE_TASK_POOL;
+pub fn cancel_store_task(uid: usize) -> Result<Option<Box<FnBox() + Send>>, String> {
+    if let PoolBackend::Steal = get_store_backend() {
+        return Err(format!("cancel task unsupported by steal pool, uid: {}", uid)); //工作窃取任务池不支持取消任务
+    }
+
+    let &(ref lock, _) = &**STORE_TASK_POOL;
     let mut task_pool = lock.lock().unwrap();
-    (*task_pool).push(task_type, priority, func, info);
-    cvar.notify_one();
+    Ok((*task_pool).cancel(uid))
 }
 
 /*
-* 线程安全的向外部任务池投递任务
+* 线程安全的取消外部任务池中未被弹出的任务，成功返回任务函数，任务已被弹出或不存在返回None，后端为工作窃取任务池时不支持取消，返回错误
 */
-pub fn cast_ext_task(task_type: TaskType, priority: u64, func: Box<FnBox()>, info: Atom) {
-    let &(ref lock, ref cvar) = &**EXT_TASK_POOL;
+pub fn cancel_ext_task(uid: usize) -> Result<Option<Box<FnBox() + Send>>, String> {
+    if let PoolBackend::Steal = get_ext_backend() {
+        return Err(format!("cancel task unsupported by steal pool, uid: {}", uid)); //工作窃取任务池不支持取消任务
+    }
+
+    let &(ref lock, _) = &**EXT_TASK_POOL;
     let mut task_pool = lock.lock().unwrap();
-    (*task_pool).push(task_type, priority, func, info);
-    cvar.notify_one();
+    Ok((*task_pool).cancel(uid))
+}
+
+/*
+* 判断指定的互斥任务池是否是后端为工作窃取任务池的全局任务池，这种互斥任务池上没有运行工作者池，加入的任务不会被执行
+*/
+pub fn is_steal_backend(sync: &Arc<(Mutex<TaskPool>, Condvar)>) -> bool {
+    (Arc::ptr_eq(sync, &JS_TASK_POOL) && get_js_backend() == PoolBackend::Steal)
+        || (Arc::ptr_eq(sync, &STORE_TASK_POOL) && get_store_backend() == PoolBackend::Steal)
+        || (Arc::ptr_eq(sync, &EXT_TASK_POOL) && get_ext_backend() == PoolBackend::Steal)
+}
+
+//获取指定后端标记对应的任务池后端
+fn backend(flag: &AtomicUsize) -> PoolBackend {
+    if flag.load(Ordering::SeqCst) == PoolBackend::Steal as usize {
+        PoolBackend::Steal
+    } else {
+        PoolBackend::Mutex
+    }
 }
\ No newline at end of file
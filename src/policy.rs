@@ -0,0 +1,311 @@
+use rand;
+use rand::Rng;
+use fnv::FnvHashMap;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::boxed::FnBox;
+use std::fmt::{Display, Formatter, Result};
+
+use task::Task;
+use task_pool::{SyncPool, AsyncPool};
+use util::now_millisecond;
+
+/*
+* 调度策略，决定任务池从同步和异步任务队列中弹出任务的顺序，延迟任务队列由任务池在同步和异步任务队列为空时处理，
+* 老化只增加同步任务队列和异步任务桶的权重，只有按权重选择的加权随机和加权轮询调度策略受老化影响，严格优先级和最早截止时间优先调度策略不受影响，
+* 但老化的最长等待时间和延迟任务提升对所有调度策略有效
+*/
+pub trait SchedulingPolicy<T = Box<FnBox() + Send>>: Send {
+    //任务加入任务池前调用，可用于设置任务的调度信息
+    fn on_push(&mut self, _task: &mut Task<T>) {}
+
+    //从同步任务池和异步任务池中选择并弹出一个任务，需要随机数时应使用任务池提供的随机数生成器，以便记录和重放，没有可弹出的任务则返回None
+    fn pop(&mut self, sync_pool: &mut SyncPool<T>, async_pool: &mut AsyncPool<T>, rng: &mut PolicyRng) -> Option<Task<T>>;
+}
+
+/*
+* 调度随机数生成器，默认使用线程随机数，设置种子后使用确定的伪随机序列，可记录生成的随机数，重放时优先使用指定的随机数
+*/
+pub struct PolicyRng {
+    seed:   Option<u64>,    //伪随机序列的当前状态，None表示使用线程随机数
+    record: bool,           //是否记录生成的随机数
+    draws:  Vec<u64>,       //已记录的随机数
+    forced: VecDeque<u64>,  //重放时指定的随机数
+}
+
+impl Display for PolicyRng {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "PolicyRng[is_seeded = {}, record = {}, draw_size = {}, forced_size = {}]",
+            self.seed.is_some(), self.record, self.draws.len(), self.forced.len())
+	}
+}
+
+impl PolicyRng {
+    //构建一个使用线程随机数的随机数生成器
+    pub fn new() -> Self {
+        PolicyRng {
+            seed:   None,
+            record: false,
+            draws:  Vec::new(),
+            forced: VecDeque::new(),
+        }
+    }
+
+    //构建一个使用指定种子的随机数生成器，相同种子生成相同的随机数序列
+    pub fn with_seed(seed: u64) -> Self {
+        let mut rng = PolicyRng::new();
+        rng.seed = Some(seed);
+        rng
+    }
+
+    //判断是否使用确定的伪随机序列
+    pub fn is_seeded(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    //设置是否记录生成的随机数，关闭时清空已记录的随机数
+    pub fn set_record(&mut self, record: bool) {
+        self.record = record;
+        if !record {
+            self.draws.clear();
+        }
+    }
+
+    //取出已记录的随机数
+    pub fn take_draws(&mut self) -> Vec<u64> {
+        self.draws.drain(..).collect()
+    }
+
+    //清空已记录的随机数
+    pub fn clear_draws(&mut self) {
+        self.draws.clear();
+    }
+
+    //指定后续生成的随机数，用于重放，超出范围的随机数会被取模
+    pub fn force(&mut self, draws: &[u64]) {
+        self.forced.extend(draws.iter().cloned());
+    }
+
+    //获取剩余的指定随机数数量
+    pub fn forced_size(&self) -> usize {
+        self.forced.len()
+    }
+
+    //生成[low, high)范围内的随机数
+    pub fn gen_range(&mut self, low: u64, high: u64) -> u64 {
+        let r = match self.forced.pop_front() {
+            Some(r) if r >= low && r < high => r,
+            Some(r) => low + r % (high - low),
+            None => {
+                match self.seed {
+                    None => rand::thread_rng().gen_range(low, high),
+                    Some(ref mut state) => low + splitmix(state) % (high - low),
+                }
+            },
+        };
+        if self.record {
+            self.draws.push(r);
+        }
+        r
+    }
+}
+
+//生成伪随机序列的下一个值
+fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/*
+* 加权随机调度策略，按任务优先级的总和随机选择任务，优先级越高被选中的概率越大，任务的权重包括老化增加的优先级
+*/
+pub struct WeightedRandomPolicy;
+
+impl Display for WeightedRandomPolicy {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "WeightedRandomPolicy")
+	}
+}
+
+impl WeightedRandomPolicy {
+    //构建一个加权随机调度策略
+    pub fn new() -> Self {
+        WeightedRandomPolicy
+    }
+}
+
+impl<T> SchedulingPolicy<T> for WeightedRandomPolicy {
+    fn pop(&mut self, sync_pool: &mut SyncPool<T>, async_pool: &mut AsyncPool<T>, rng: &mut PolicyRng) -> Option<Task<T>> {
+        let sw = sync_pool.weight();
+        let w = sw + async_pool.weight();
+        if w == 0 {
+            return None;
+        }
+
+        //判断从同步还是异步任务队列中弹出
+        let r = rng.gen_range(0, w);
+        if r < sw {
+            //从同步任务队列中弹出
+            sync_pool.pop(r)
+        } else {
+            //从异步任务队列中弹出
+            async_pool.pop(r - sw)
+        }
+    }
+}
+
+/*
+* 严格优先级调度策略，总是弹出优先级最高的任务，优先级相同时同步任务优先，同优先级内先进先出，队列老化不影响选择，老化后提升的延迟任务按老化后的优先级选择
+*/
+pub struct StrictPriorityPolicy;
+
+impl Display for StrictPriorityPolicy {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "StrictPriorityPolicy")
+	}
+}
+
+impl StrictPriorityPolicy {
+    //构建一个严格优先级调度策略
+    pub fn new() -> Self {
+        StrictPriorityPolicy
+    }
+}
+
+impl<T> SchedulingPolicy<T> for StrictPriorityPolicy {
+    fn pop(&mut self, sync_pool: &mut SyncPool<T>, async_pool: &mut AsyncPool<T>, _rng: &mut PolicyRng) -> Option<Task<T>> {
+        //同步任务只需比较各队列头，异步任务从优先级最高的非空桶中弹出
+        let sp = sync_pool.peek_by_key(|t| Reverse(t.get_aged_priority())).map(|t| t.get_aged_priority());
+        let ap = async_pool.highest_priority();
+        match (sp, ap) {
+            (Some(s), Some(a)) if a > s => async_pool.pop_highest(),
+            (Some(s), _) => sync_pool.pop_priority(s),
+            (None, Some(_)) => async_pool.pop_highest(),
+            (None, None) => None,
+        }
+    }
+}
+
+/*
+* 加权轮询调度策略，使用平滑加权轮询在各优先级的同步任务队列和异步任务队列之间选择，结果可预测且不会连续偏向同一队列，
+* 队列权重包括老化增加的优先级，异步任务队列内先进先出
+*/
+pub struct WeightedRoundRobinPolicy {
+    sync_current:   FnvHashMap<u64, i64>,   //各优先级同步任务队列的当前权重
+    async_current:  i64,                    //异步任务队列的当前权重
+}
+
+impl Display for WeightedRoundRobinPolicy {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "WeightedRoundRobinPolicy[sync_size = {}, async_current = {}]",
+            self.sync_current.len(), self.async_current)
+	}
+}
+
+impl WeightedRoundRobinPolicy {
+    //构建一个加权轮询调度策略
+    pub fn new() -> Self {
+        WeightedRoundRobinPolicy {
+            sync_current:   FnvHashMap::default(),
+            async_current:  0,
+        }
+    }
+}
+
+impl<T> SchedulingPolicy<T> for WeightedRoundRobinPolicy {
+    fn pop(&mut self, sync_pool: &mut SyncPool<T>, async_pool: &mut AsyncPool<T>, _rng: &mut PolicyRng) -> Option<Task<T>> {
+        let aw = async_pool.weight() as i64;
+        if sync_pool.size() == 0 && aw == 0 {
+            return None;
+        }
+
+        //移除已为空的同步任务队列的当前权重
+        self.sync_current.retain(|priority, _| !sync_pool.is_queue_empty(*priority));
+
+        //增加所有非空队列的当前权重，并选择当前权重最大的队列
+        let mut total = aw;
+        let mut selected: Option<u64> = None;
+        let mut max = if aw > 0 {
+            self.async_current += aw;
+            self.async_current
+        } else {
+            self.async_current = 0;
+            i64::min_value()
+        };
+        {
+            let sync_current = &mut self.sync_current;
+            sync_pool.each_queue(|priority, weight| {
+                let weight = weight as i64;
+                total += weight;
+                let current = sync_current.entry(priority).or_insert(0);
+                *current += weight;
+                if *current > max {
+                    max = *current;
+                    selected = Some(priority);
+                }
+            });
+        }
+
+        //减少被选中队列的当前权重
+        match selected {
+            Some(priority) => {
+                if let Some(current) = self.sync_current.get_mut(&priority) {
+                    *current -= total;
+                }
+                sync_pool.pop_priority(priority)
+            },
+            None => {
+                self.async_current -= total;
+                async_pool.pop_front()
+            },
+        }
+    }
+}
+
+/*
+* 最早截止时间优先调度策略，任务加入时根据优先级设置截止时间，优先级越高截止时间越近，总是弹出截止时间最早的任务，截止时间不受老化影响
+*/
+pub struct EarliestDeadlinePolicy {
+    window: u64, //优先级为1的任务的相对截止时间，单位ms
+}
+
+impl Display for EarliestDeadlinePolicy {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "EarliestDeadlinePolicy[window = {}]", self.window)
+	}
+}
+
+impl EarliestDeadlinePolicy {
+    //构建一个最早截止时间优先调度策略，任务的相对截止时间为window / priority毫秒
+    pub fn new(window: u64) -> Self {
+        EarliestDeadlinePolicy {
+            window: window,
+        }
+    }
+}
+
+impl<T> SchedulingPolicy<T> for EarliestDeadlinePolicy {
+    fn on_push(&mut self, task: &mut Task<T>) {
+        let priority = if task.get_priority() == 0 {
+            1
+        } else {
+            task.get_priority()
+        };
+        task.set_deadline(now_millisecond() as u64 + self.window / priority);
+    }
+
+    fn pop(&mut self, sync_pool: &mut SyncPool<T>, async_pool: &mut AsyncPool<T>, _rng: &mut PolicyRng) -> Option<Task<T>> {
+        //同一优先级的同步任务截止时间随加入顺序递增，只需比较各队列头，异步任务使用截止时间堆
+        let sd = sync_pool.peek_by_key(|t| t.get_deadline()).map(|t| t.get_deadline());
+        let ad = async_pool.earliest_deadline();
+        match (sd, ad) {
+            (Some(s), Some(a)) if a < s => async_pool.pop_earliest_deadline(),
+            (Some(_), _) => sync_pool.pop_by_key(|t| t.get_deadline()),
+            (None, Some(_)) => async_pool.pop_earliest_deadline(),
+            (None, None) => None,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::mem;
+use std::thread;
+use std::boxed::FnBox;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, Condvar};
+use std::panic::{self, AssertUnwindSafe};
+use std::result::Result as GenResult;
+use std::fmt::{Display, Formatter, Result};
+
+use pi_lib::atom::Atom;
+use task::TaskType;
+use task_pool::{TaskPool, push_task};
+
+/*
+* 作用域内部状态
+*/
+struct ScopeState {
+    pending:    usize,      //未结束的任务数量
+    panicked:   usize,      //执行时发生异常的任务数量
+    uids:       Vec<usize>, //加入的任务唯一id
+}
+
+/*
+* 作用域共享数据
+*/
+struct ScopeShared {
+    state:  Mutex<ScopeState>,  //作用域内部状态
+    cvar:   Condvar,            //等待任务结束的条件变量
+}
+
+/*
+* 作用域任务守护，任务函数执行结束、发生异常或未执行就被释放时，通知作用域任务结束
+*/
+struct MemberGuard {
+    shared: Arc<ScopeShared>,   //所属作用域
+    ran:    bool,               //是否已开始执行
+}
+
+impl Drop for MemberGuard {
+    fn drop(&mut self) {
+        let panicked = self.ran && thread::panicking();
+        let mut state = self.shared.state.lock().unwrap();
+        state.pending -= 1;
+        if panicked {
+            state.panicked += 1;
+        }
+        self.shared.cvar.notify_all();
+    }
+}
+
+/*
+* 作用域任务，任务未执行就被释放时，先释放任务函数，再通过任务守护通知作用域任务结束，保证作用域结束前任务函数借用的数据已不再被访问
+*/
+struct ScopedTask<F: FnOnce()> {
+    func:   Option<F>,      //任务函数，执行时取出
+    guard:  MemberGuard,    //任务守护
+}
+
+impl<F: FnOnce()> Drop for ScopedTask<F> {
+    fn drop(&mut self) {
+        self.func.take(); //在任务守护之前释放任务函数
+    }
+}
+
+/*
+* 任务作用域，作用域内加入的任务可以借用调用者栈上的数据，作用域结束前保证所有任务都已结束或已从任务池中移除
+*/
+pub struct Scope<'env> {
+    sync:   Arc<(Mutex<TaskPool>, Condvar)>,    //任务池
+    shared: Arc<ScopeShared>,                   //作用域共享数据
+    marker: PhantomData<&'env mut &'env ()>,    //借用数据的生命周期
+}
+
+impl<'env> Display for Scope<'env> {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+        let state = self.shared.state.lock().unwrap();
+		write!(f, "Scope[pending = {}, panicked = {}, task_size = {}]",
+            state.pending, state.panicked, state.uids.len())
+	}
+}
+
+impl<'env> Scope<'env> {
+    //获取未结束的任务数量
+    pub fn pending(&self) -> usize {
+        self.shared.state.lock().unwrap().pending
+    }
+
+    //向任务池加入一个作用域任务，任务函数可以借用作用域外的数据，返回任务唯一id
+    pub fn spawn<F>(&self, task_type: TaskType, priority: u64, func: F, info: Atom) -> GenResult<usize, String>
+        where F: FnOnce() + Send + 'env {
+        self.shared.state.lock().unwrap().pending += 1;
+        let task = ScopedTask {
+            func:   Some(func),
+            guard:  MemberGuard {
+                shared: self.shared.clone(),
+                ran:    false,
+            },
+        };
+        let func: Box<FnBox() + Send + 'env> = Box::new(move || {
+            let mut task = task;
+            if let Some(func) = task.func.take() {
+                task.guard.ran = true;
+                func();
+            }
+        });
+        //作用域结束前会等待任务结束或移除任务，所以任务函数不会在借用的数据释放后执行
+        let func: Box<FnBox() + Send> = unsafe { mem::transmute(func) };
+        let uid = push_task(&self.sync, task_type, move |task_pool| {
+            task_pool.push(task_type, priority, func, info)
+        })?;
+        self.shared.state.lock().unwrap().uids.push(uid);
+        Ok(uid)
+    }
+
+    //结束作用域，取出还在任务池中的任务，正常结束时在当前线程执行，发生异常时直接释放，然后等待工作者正在执行的任务结束
+    fn join(&self, is_run: bool) {
+        let uids = mem::replace(&mut self.shared.state.lock().unwrap().uids, Vec::new());
+        for uid in uids {
+            let func = {
+                let &(ref lock, ref cvar) = &*self.sync;
+                let mut task_pool = lock.lock().unwrap();
+                let func = (*task_pool).cancel(uid);
+                if func.is_some() && (*task_pool).blocked_size() > 0 {
+                    //唤醒等待任务池空间的调用者
+                    cvar.notify_all();
+                }
+                func
+            };
+            if let Some(func) = func {
+                if is_run {
+                    //任务的异常由任务守护记录
+                    let _ = panic::catch_unwind(AssertUnwindSafe(move || func()));
+                }
+            }
+        }
+
+        let mut state = self.shared.state.lock().unwrap();
+        while state.pending > 0 {
+            state = self.shared.cvar.wait(state).unwrap();
+        }
+    }
+}
+
+/*
+* 在指定任务池上创建任务作用域，作用域内加入的任务可以借用调用者栈上的数据，返回前保证所有任务都已结束，
+* 还在任务池中的任务会在当前线程中执行，所以没有运行工作者池时也不会阻塞，有任务执行时发生异常则返回错误，
+* 作用域函数发生异常时，还在任务池中的任务会被移除，等待正在执行的任务结束后继续抛出异常，
+* 还在任务池中的任务包括等待中的独占任务都会在当前线程中执行，所以在同一个任务池的任务中创建作用域时，不会因为独占任务等待而死锁
+*/
+pub fn scope<'env, F, R>(sync: &Arc<(Mutex<TaskPool>, Condvar)>, f: F) -> GenResult<R, String>
+    where F: FnOnce(&Scope<'env>) -> R {
+    let scope = Scope {
+        sync:   sync.clone(),
+        shared: Arc::new(ScopeShared {
+            state: Mutex::new(ScopeState {
+                pending:    0,
+                panicked:   0,
+                uids:       Vec::new(),
+            }),
+            cvar: Condvar::new(),
+        }),
+        marker: PhantomData,
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| f(&scope))) {
+        Err(e) => {
+            scope.join(false);
+            panic::resume_unwind(e);
+        },
+        Ok(r) => {
+            scope.join(true);
+            let panicked = scope.shared.state.lock().unwrap().panicked;
+            if panicked > 0 {
+                return Err(format!("scoped task panic, count: {}", panicked));
+            }
+            Ok(r)
+        },
+    }
+}
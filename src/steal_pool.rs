@@ -0,0 +1,218 @@
+use std::cell::RefCell;
+use std::boxed::FnBox;
+use std::time::{Instant, Duration};
+use std::sync::{Mutex, Condvar, RwLock};
+use std::result::Result as GenResult;
+use std::fmt::{Display, Formatter, Result};
+use std::sync::atomic::{Ordering, AtomicUsize};
+
+use crossbeam_deque::{Injector, Worker as Deque, Stealer, Steal};
+
+use pi_lib::atom::Atom;
+use task::{TaskType, Task};
+use worker::TaskBackend;
+use context;
+
+/*
+* 工作者没有任务时的最长等待时间，单位ms，有任务加入时会被立即唤醒
+*/
+const STEAL_POOL_WAIT_TIMEOUT: u64 = 100;
+
+/*
+* 窃取任务池编号计数器
+*/
+static STEAL_POOL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    //当前线程作为工作者时所属的窃取任务池编号和本地任务队列
+    static LOCAL: RefCell<Option<(usize, Deque<Task>)>> = RefCell::new(None);
+}
+
+/*
+* 工作窃取任务池，每个工作者有一个本地任务队列，外部线程加入的任务进入全局任务队列，工作者加入的任务进入自己的本地任务队列，
+* 工作者依次从本地任务队列、全局任务队列和其它工作者的本地任务队列中获取任务，所有任务队列都为空时才获取延迟任务
+* 任务按加入顺序执行，不区分优先级和任务类型，不支持串行任务、定时任务和取消任务
+*/
+pub struct StealPool {
+    uid:        usize,                              //窃取任务池编号
+    counter:    AtomicUsize,                        //任务唯一id计数器
+    len:        AtomicUsize,                        //任务数量，包括延迟任务和所有本地任务队列中的任务
+    injector:   Injector<Task>,                     //全局任务队列
+    delay:      Injector<Task>,                     //全局延迟任务队列
+    stealers:   RwLock<Vec<(u32, Stealer<Task>)>>,  //工作者编号和本地任务队列的窃取者
+    sleeping:   AtomicUsize,                        //正在等待任务的工作者数量
+    lock:       Mutex<()>,                          //等待任务的锁
+    cvar:       Condvar,                            //等待任务的条件变量
+}
+
+impl Display for StealPool {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "StealPool[uid = {}, counter = {}, size = {}, worker_size = {}, sleeping = {}]",
+            self.uid, self.counter.load(Ordering::Relaxed), self.size(), self.stealers.read().unwrap().len(), self.sleeping.load(Ordering::Relaxed))
+	}
+}
+
+impl StealPool {
+    //构建一个工作窃取任务池
+    pub fn new() -> Self {
+        StealPool {
+            uid:        STEAL_POOL_COUNTER.fetch_add(1, Ordering::Relaxed) + 1,
+            counter:    AtomicUsize::new(0),
+            len:        AtomicUsize::new(0),
+            injector:   Injector::new(),
+            delay:      Injector::new(),
+            stealers:   RwLock::new(Vec::new()),
+            sleeping:   AtomicUsize::new(0),
+            lock:       Mutex::new(()),
+            cvar:       Condvar::new(),
+        }
+    }
+
+    //获取任务数量
+    pub fn size(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    //向任务池加入一个任务，返回任务唯一id，优先级为0的任务为延迟任务，任务类型只用于记录
+    pub fn push(&self, task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom) -> GenResult<usize, String> {
+        match task_type {
+            TaskType::Empty | TaskType::Exclusive => {
+                return Err(format!("steal pool push failed, invalid task type, task_type: {:?}", task_type));
+            },
+            _ => (),
+        }
+
+        let uid = self.counter.fetch_add(1, Ordering::Relaxed) + 1; //任务唯一id从1开始，0表示无效任务
+        let mut task = Task::new();
+        task.set_uid(uid);
+        task.set_priority(priority);
+        task.set_payload(Some(func));
+        task.set_info(info);
+        task.set_type(task_type);
+        task.set_time(Some(Instant::now()));
+        task.set_context(context::current());
+
+        //先增加任务数量，保证等待中的工作者不会错过任务
+        self.len.fetch_add(1, Ordering::SeqCst);
+        if priority == 0 {
+            self.delay.push(task);
+        } else {
+            //当前线程是本任务池的工作者，则加入本地任务队列
+            let pool = self.uid;
+            let task = LOCAL.with(move |local| {
+                match *local.borrow() {
+                    Some((uid, ref deque)) if uid == pool => {
+                        deque.push(task);
+                        None
+                    },
+                    _ => Some(task),
+                }
+            });
+            if let Some(t) = task {
+                self.injector.push(t);
+            }
+        }
+        self.notify();
+        Ok(uid)
+    }
+
+    //获取一个任务
+    fn find(&self) -> Option<Task> {
+        let pool = self.uid;
+        let task = LOCAL.with(|local| {
+            let local = local.borrow();
+            let deque = match *local {
+                Some((uid, ref deque)) if uid == pool => Some(deque),
+                _ => None,
+            };
+
+            //从本地任务队列中获取
+            if let Some(d) = deque {
+                if let Some(t) = d.pop() {
+                    return Some(t);
+                }
+            }
+
+            loop {
+                //从全局任务队列中获取，工作者会同时获取一批任务到本地任务队列
+                let steal = match deque {
+                    Some(d) => self.injector.steal_batch_and_pop(d),
+                    None => self.injector.steal(),
+                };
+
+                //从其它工作者的本地任务队列中窃取
+                let steal = steal.or_else(|| {
+                    self.stealers.read().unwrap().iter().map(|&(_, ref s)| s.steal()).collect()
+                });
+                match steal {
+                    Steal::Success(t) => return Some(t),
+                    Steal::Empty => return None,
+                    Steal::Retry => continue,
+                }
+            }
+        });
+        if task.is_some() {
+            return task;
+        }
+
+        //所有任务队列都为空，则获取延迟任务
+        loop {
+            match self.delay.steal() {
+                Steal::Success(t) => return Some(t),
+                Steal::Empty => return None,
+                Steal::Retry => continue,
+            }
+        }
+    }
+
+    //唤醒一个等待任务的工作者
+    fn notify(&self) {
+        if self.sleeping.load(Ordering::SeqCst) > 0 {
+            let _lock = self.lock.lock().unwrap();
+            self.cvar.notify_one();
+        }
+    }
+}
+
+impl TaskBackend for StealPool {
+    fn attach(&self, worker: u32) {
+        let deque = Deque::new_fifo();
+        self.stealers.write().unwrap().push((worker, deque.stealer()));
+        LOCAL.with(|local| {
+            *local.borrow_mut() = Some((self.uid, deque));
+        });
+    }
+
+    fn detach(&self, worker: u32) {
+        self.stealers.write().unwrap().retain(|&(uid, _)| uid != worker);
+        let local = LOCAL.with(|local| local.borrow_mut().take());
+        if let Some((_, deque)) = local {
+            //将本地任务队列中的任务移入全局任务队列
+            let mut count = 0;
+            while let Some(t) = deque.pop() {
+                self.injector.push(t);
+                count += 1;
+            }
+            if count > 0 {
+                self.notify();
+            }
+        }
+    }
+
+    fn acquire(&self, task: &mut Task) -> bool {
+        if let Some(mut t) = self.find() {
+            self.len.fetch_sub(1, Ordering::SeqCst);
+            t.move_to(task); //填充任务
+            return true;
+        }
+
+        //没有任务，则等待任务加入或超时
+        let lock = self.lock.lock().unwrap();
+        self.sleeping.fetch_add(1, Ordering::SeqCst);
+        if self.len.load(Ordering::SeqCst) == 0 {
+            let _ = self.cvar.wait_timeout(lock, Duration::from_millis(STEAL_POOL_WAIT_TIMEOUT)).unwrap();
+        }
+        self.sleeping.fetch_sub(1, Ordering::SeqCst);
+        false
+    }
+}
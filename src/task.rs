@@ -1,9 +1,11 @@
 use std::boxed::FnBox;
-use std::mem::transmute;
+use std::time::Instant;
 use std::collections::VecDeque;
 use std::fmt::{Display, Formatter, Result};
 
 use pi_lib::atom::Atom;
+use observer::PoolObserver;
+use context::{TaskContext, run_with};
 
 /*
 * 任务类型
@@ -14,43 +16,80 @@ pub enum TaskType {
     Async,      //异步任务
     Sync,       //同步任务
     SyncImme,   //同步立即任务
+    Exclusive,  //独占任务，只在任务池没有其它任务执行时执行，执行期间不分派其它任务，有独占任务等待时，正在执行的任务不能等待同一个任务池中的其它任务，否则死锁
 }
 
 unsafe impl Send for TaskType {}
 
 /*
-* 任务结构
+* 任务结构，负载默认为任务函数，也可以是任意类型的数据
 */
-pub struct Task {
+pub struct Task<T = Box<FnBox() + Send>> {
+    uid:            usize,              //任务唯一id
     priority:       u64,                //任务优先级
-    func:           (usize, usize),     //任务函数
+    boost:          u64,                //老化增加的优先级，由任务池在延迟任务老化后设置
+    task_type:      TaskType,           //任务类型，由任务池设置
+    deadline:       u64,                //任务截止时间，单位ms，由调度策略设置
+    payload:        Option<T>,          //任务负载，只能被取出一次
     info:           Atom,               //任务信息
+    serial:         Option<Atom>,       //串行任务的键，同一个键的任务按顺序串行执行
+    time:           Option<Instant>,    //任务进入就绪队列的时间，由任务池设置，用于统计排队等待时长
+    sheddable:      bool,               //任务池过载时是否可以丢弃
+    tags:           Vec<Atom>,          //任务标签，用于批量移除任务
+    observer:       Option<PoolObserver>,//任务所属任务池的观察者，由任务池弹出任务时设置
+    context:        Option<TaskContext>,//任务上下文，由任务池加入任务时捕获，执行任务时安装
 }
 
-unsafe impl Sync for Task {} //声明保证多线程安全性
-
-impl Display for Task {
+impl<T> Display for Task<T> {
 	fn fmt(&self, f: &mut Formatter) -> Result {
-		write!(f, "Task[priority = {}, func = {:?}, info = {}]", self.priority, self.func, *self.info)
+		write!(f, "Task[uid = {}, priority = {}, payload = {}, info = {}, serial = {:?}]", self.uid, self.priority, self.payload.is_some(), *self.info, self.serial)
 	}
 }
 
-impl Task {
+impl<T> Task<T> {
     pub fn new() -> Self {
         Task {
+            uid:        0,
             priority:   0,
-            func:       (0, 0),
+            boost:      0,
+            task_type:  TaskType::Empty,
+            deadline:   0,
+            payload:    None,
             info:       Atom::from(""),
+            serial:     None,
+            time:       None,
+            sheddable:  false,
+            tags:       Vec::new(),
+            observer:   None,
+            context:    None,
         }
     }
 
-    pub fn copy_to(&self, dest: &mut Self) {
-        //复制其它成员
+    //将任务移动到目标任务，负载被移出，其它成员被复制
+    pub fn move_to(&mut self, dest: &mut Self) {
+        dest.payload = self.payload.take();
+        dest.uid = self.uid;
         dest.priority = self.priority;
-        dest.func = self.func;
+        dest.boost = self.boost;
+        dest.task_type = self.task_type;
+        dest.deadline = self.deadline;
         dest.info = self.info.clone();
+        dest.serial = self.serial.clone();
+        dest.time = self.time;
+        dest.sheddable = self.sheddable;
+        dest.tags = self.tags.clone();
+        dest.observer = self.observer.clone();
+        dest.context = self.context.clone();
     }
     
+    pub fn get_uid(&self) -> usize {
+        self.uid
+    }
+
+    pub fn set_uid(&mut self, uid: usize) {
+        self.uid = uid;
+    }
+
     pub fn get_priority(&self) -> u64 {
         self.priority
     }
@@ -58,55 +97,158 @@ impl Task {
     pub fn set_priority(&mut self, priority: u64) {
         self.priority = priority;
     }
+
+    pub fn get_boost(&self) -> u64 {
+        self.boost
+    }
+
+    pub fn set_boost(&mut self, boost: u64) {
+        self.boost = boost;
+    }
+
+    //获取老化后的优先级，即任务优先级加上老化增加的优先级，任务池按老化后的优先级选择任务队列
+    pub fn get_aged_priority(&self) -> u64 {
+        self.priority + self.boost
+    }
     
-    pub fn set_func(&mut self, func: Option<Box<FnBox()>>) {
-        match func {
-            Some(f) => {
-                let (x, y): (usize, usize) = unsafe { transmute(f) };
-                self.func.0 = x;
-                self.func.1 = y;
-            },
-            None => (),
-        }
+    pub fn get_type(&self) -> TaskType {
+        self.task_type
+    }
+
+    pub fn set_type(&mut self, task_type: TaskType) {
+        self.task_type = task_type;
+    }
+
+    pub fn get_deadline(&self) -> u64 {
+        self.deadline
+    }
+
+    pub fn set_deadline(&mut self, deadline: u64) {
+        self.deadline = deadline;
+    }
+
+    pub fn get_payload(&self) -> Option<&T> {
+        self.payload.as_ref()
+    }
+
+    pub fn set_payload(&mut self, payload: Option<T>) {
+        self.payload = payload;
+    }
+
+    //取出任务负载，取出后任务不会再被执行
+    pub fn take_payload(&mut self) -> Option<T> {
+        self.payload.take()
     }
 
     pub fn get_info(&self) -> &str {
         self.info.as_str()
     }
 
+    pub fn get_info_atom(&self) -> &Atom {
+        &self.info
+    }
+
     pub fn set_info(&mut self, info: Atom) {
         self.info = info;
     }
 
+    pub fn get_serial(&self) -> Option<&Atom> {
+        self.serial.as_ref()
+    }
+
+    pub fn set_serial(&mut self, serial: Option<Atom>) {
+        self.serial = serial;
+    }
+
+    pub fn is_serial(&self) -> bool {
+        self.serial.is_some()
+    }
+
+    pub fn get_time(&self) -> Option<Instant> {
+        self.time
+    }
+
+    pub fn set_time(&mut self, time: Option<Instant>) {
+        self.time = time;
+    }
+
+    pub fn is_sheddable(&self) -> bool {
+        self.sheddable
+    }
+
+    pub fn set_sheddable(&mut self, sheddable: bool) {
+        self.sheddable = sheddable;
+    }
+
+    pub fn get_tags(&self) -> &[Atom] {
+        &self.tags[..]
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<Atom>) {
+        self.tags = tags;
+    }
+
+    pub fn has_tag(&self, tag: &Atom) -> bool {
+        self.tags.contains(tag)
+    }
+
+    pub fn get_observer(&self) -> Option<&PoolObserver> {
+        self.observer.as_ref()
+    }
+
+    pub fn set_observer(&mut self, observer: Option<PoolObserver>) {
+        self.observer = observer;
+    }
+
+    pub fn get_context(&self) -> Option<&TaskContext> {
+        self.context.as_ref()
+    }
+
+    pub fn set_context(&mut self, context: Option<TaskContext>) {
+        self.context = context;
+    }
+
     pub fn reset(&mut self) {
+        self.uid = 0;
         self.priority = 0;
-        self.func = (0, 0);
+        self.boost = 0;
+        self.task_type = TaskType::Empty;
+        self.deadline = 0;
+        self.payload = None;
         self.info = Atom::from("");
+        self.serial = None;
+        self.time = None;
+        self.sheddable = false;
+        self.tags.clear();
+        self.observer = None;
+        self.context = None;
     }
 
-    pub fn run(&self) {
-        if self.func == (0, 0) {
-            return;
+}
+
+impl Task {
+    //在任务上下文中执行任务函数，任务函数被取出，不会被重复执行
+    pub fn run(&mut self) {
+        if let Some(func) = self.payload.take() {
+            run_with(self.context.clone(), move || func());
         }
-        let func: Box<FnBox()> = unsafe { transmute(self.func) };
-        func();
     }
 }
 
 /*
 * 任务缓存结构
 */
-pub struct TaskCache {
-    cache: VecDeque<Task>, //任务缓存
+pub struct TaskCache<T = Box<FnBox() + Send>> {
+    cache: VecDeque<Task<T>>, //任务缓存
 }
 
-impl Display for TaskCache {
+impl<T> Display for TaskCache<T> {
 	fn fmt(&self, f: &mut Formatter) -> Result {
 		write!(f, "TaskCache[size = {}]", self.cache.len())
 	}
 }
 
-impl TaskCache {
+impl<T> TaskCache<T> {
     pub fn new(len: u32) -> Self {
         if len < 1 {
             panic!("invalid task cache size");
@@ -121,14 +263,14 @@ impl TaskCache {
         }
     }
     
-    pub fn pop(&mut self) -> Task {
+    pub fn pop(&mut self) -> Task<T> {
         match self.cache.pop_front() {
             Some(e) => e,
             None => Task::new(),
         }
     }
     
-    pub fn push(&mut self, mut entry: Task) {
+    pub fn push(&mut self, mut entry: Task<T>) {
         entry.reset();
         self.cache.push_back(entry);
     }
@@ -0,0 +1,380 @@
+use std::boxed::FnBox;
+use std::time::{Instant, Duration};
+use std::sync::{Arc, Mutex, Condvar};
+use std::result::Result as GenResult;
+use std::fmt::{Display, Formatter, Result};
+use std::sync::atomic::{Ordering, AtomicUsize};
+
+use pi_lib::atom::Atom;
+use task::TaskType;
+use task_pool::{TaskPool, push_task};
+
+/*
+* 节点任务的加入状态
+*/
+const NODE_PUSHING: usize = 0;  //正在加入任务池
+const NODE_PUSHED: usize = 1;   //已加入任务池
+const NODE_DROPPED: usize = 2;  //加入任务池时被释放
+
+/*
+* 任务图节点状态
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeStatus {
+    Waiting,                //等待前置节点完成
+    Submitted,              //已加入任务池
+    Completed,              //执行成功
+    Failed(String),         //执行失败，包括返回错误、发生异常、加入任务池失败和未执行就被释放
+    Skipped(Vec<usize>),    //因前置节点失败而跳过，值为从失败节点到该节点的路径
+}
+
+/*
+* 任务图节点
+*/
+struct GraphNode {
+    name:       Atom,                                                 //节点名
+    sync:       Arc<(Mutex<TaskPool>, Condvar)>,                      //节点任务加入的任务池
+    task_type:  TaskType,                                             //节点任务类型
+    priority:   u64,                                                  //节点任务优先级
+    func:       Option<Box<FnBox() -> GenResult<(), String> + Send>>, //节点任务函数，加入任务池时取出
+    pending:    usize,                                                //未完成的前置节点数量
+    children:   Vec<usize>,                                           //后续节点
+    status:     NodeStatus,                                           //节点状态
+}
+
+/*
+* 任务图，节点只能依赖已加入的节点，所以不会有环，每个节点在所有前置节点都执行成功后才加入自己的任务池，
+* 节点失败时跳过所有后续节点
+*/
+pub struct TaskGraph {
+    nodes:      Vec<GraphNode>,                                     //节点列表，下标为节点id
+    callback:   Option<Box<FnBox(Vec<(Atom, NodeStatus)>) + Send>>, //完成回调
+}
+
+impl Display for TaskGraph {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "TaskGraph[size = {}]", self.nodes.len())
+	}
+}
+
+impl TaskGraph {
+    //构建一个任务图
+    pub fn new() -> Self {
+        TaskGraph {
+            nodes:      Vec::new(),
+            callback:   None,
+        }
+    }
+
+    //获取节点数量
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    //加入一个依赖指定节点的节点，节点任务返回错误或发生异常时节点失败，返回节点id，依赖的节点不存在则返回错误
+    pub fn add_node(&mut self, name: Atom, sync: &Arc<(Mutex<TaskPool>, Condvar)>, task_type: TaskType, priority: u64,
+                    func: Box<FnBox() -> GenResult<(), String> + Send>, deps: &[usize]) -> GenResult<usize, String> {
+        let id = self.nodes.len();
+        let mut pending = 0;
+        for dep in deps {
+            if *dep >= id {
+                return Err(format!("add graph node failed, invalid dependency, name: {}, dep: {}", *name, dep));
+            }
+            if !self.nodes[*dep].children.contains(&id) {
+                self.nodes[*dep].children.push(id);
+                pending += 1;
+            }
+        }
+
+        self.nodes.push(GraphNode {
+            name:       name,
+            sync:       sync.clone(),
+            task_type:  task_type,
+            priority:   priority,
+            func:       Some(func),
+            pending:    pending,
+            children:   Vec::new(),
+            status:     NodeStatus::Waiting,
+        });
+        Ok(id)
+    }
+
+    //设置完成回调，所有节点都完成、失败或跳过后调用，参数为各节点的节点名和状态
+    pub fn set_callback(&mut self, callback: Box<FnBox(Vec<(Atom, NodeStatus)>) + Send>) {
+        self.callback = Some(callback);
+    }
+
+    //运行任务图，将没有前置节点的节点加入任务池，返回任务图句柄
+    pub fn run(self) -> GraphHandle {
+        let ready: Vec<usize> = self.nodes.iter().enumerate().filter(|&(_, n)| n.pending == 0).map(|(id, _)| id).collect();
+        let handle = GraphHandle {
+            shared: Arc::new(GraphShared {
+                state: Mutex::new(GraphState {
+                    finished:   0,
+                    nodes:      self.nodes,
+                    callback:   self.callback,
+                }),
+                cvar: Condvar::new(),
+            }),
+        };
+
+        let submits = {
+            let mut state = handle.shared.state.lock().unwrap();
+            ready.into_iter().map(|id| state.take_submit(id)).collect()
+        };
+        GraphShared::submit(&handle.shared, submits);
+        handle.shared.check_done();
+        handle
+    }
+}
+
+/*
+* 待加入任务池的节点
+*/
+struct Submit {
+    id:         usize,                                                //节点id
+    sync:       Arc<(Mutex<TaskPool>, Condvar)>,                      //任务池
+    task_type:  TaskType,                                             //任务类型
+    priority:   u64,                                                  //优先级
+    func:       Option<Box<FnBox() -> GenResult<(), String> + Send>>, //任务函数
+    info:       Atom,                                                 //任务信息
+}
+
+/*
+* 运行中的任务图状态
+*/
+struct GraphState {
+    finished:   usize,                                              //已结束的节点数量
+    nodes:      Vec<GraphNode>,                                     //节点列表
+    callback:   Option<Box<FnBox(Vec<(Atom, NodeStatus)>) + Send>>, //完成回调
+}
+
+impl GraphState {
+    //取出待加入任务池的节点
+    fn take_submit(&mut self, id: usize) -> Submit {
+        let node = &mut self.nodes[id];
+        node.status = NodeStatus::Submitted;
+        Submit {
+            id:         id,
+            sync:       node.sync.clone(),
+            task_type:  node.task_type,
+            priority:   node.priority,
+            func:       node.func.take(),
+            info:       node.name.clone(),
+        }
+    }
+
+    //获取所有节点的节点名和状态
+    fn statuses(&self) -> Vec<(Atom, NodeStatus)> {
+        self.nodes.iter().map(|n| (n.name.clone(), n.status.clone())).collect()
+    }
+}
+
+/*
+* 运行中的任务图共享数据
+*/
+struct GraphShared {
+    state:  Mutex<GraphState>,  //任务图状态
+    cvar:   Condvar,            //等待任务图的条件变量
+}
+
+impl GraphShared {
+    //节点结束，成功则加入所有前置节点都已完成的后续节点，失败则跳过所有后续节点
+    fn complete(shared: &Arc<GraphShared>, id: usize, result: GenResult<(), String>) {
+        let submits = match result {
+            Ok(_) => shared.succeed(id),
+            Err(e) => {
+                shared.fail(id, e);
+                Vec::new()
+            },
+        };
+        GraphShared::submit(&shared, submits);
+        shared.check_done();
+    }
+
+    //记录节点成功，返回所有前置节点都已完成的后续节点
+    fn succeed(&self, id: usize) -> Vec<Submit> {
+        let mut state = self.state.lock().unwrap();
+        state.finished += 1;
+        state.nodes[id].status = NodeStatus::Completed;
+        let mut submits = Vec::new();
+        let children = state.nodes[id].children.clone();
+        for child in children {
+            state.nodes[child].pending -= 1;
+            if state.nodes[child].pending == 0 && state.nodes[child].status == NodeStatus::Waiting {
+                submits.push(state.take_submit(child));
+            }
+        }
+        submits
+    }
+
+    //记录节点失败，跳过所有后续节点，并记录从失败节点到后续节点的路径
+    fn fail(&self, id: usize, e: String) {
+        let mut state = self.state.lock().unwrap();
+        state.finished += 1;
+        state.nodes[id].status = NodeStatus::Failed(e);
+        let mut stack = vec![(id, vec![id])];
+        while let Some((parent, path)) = stack.pop() {
+            let children = state.nodes[parent].children.clone();
+            for child in children {
+                if state.nodes[child].status != NodeStatus::Waiting {
+                    continue;
+                }
+
+                let mut p = path.clone();
+                p.push(child);
+                state.nodes[child].status = NodeStatus::Skipped(p.clone());
+                state.nodes[child].func = None;
+                state.finished += 1;
+                stack.push((child, p));
+            }
+        }
+    }
+
+    //将节点加入任务池，加入失败则节点失败
+    fn submit(shared: &Arc<GraphShared>, submits: Vec<Submit>) {
+        for submit in submits {
+            let id = submit.id;
+            let func = match submit.func {
+                Some(f) => f,
+                None => continue,
+            };
+            let push = Arc::new(AtomicUsize::new(NODE_PUSHING));
+            let guard = NodeGuard {
+                shared: shared.clone(),
+                id:     id,
+                push:   push.clone(),
+                ran:    false,
+                result: None,
+            };
+            let task = Box::new(move || {
+                let mut guard = guard;
+                guard.ran = true;
+                guard.result = Some(func());
+            });
+            let (task_type, priority, info) = (submit.task_type, submit.priority, submit.info);
+            let result = push_task(&submit.sync, task_type, move |task_pool| {
+                task_pool.push(task_type, priority, task, info)
+            }).map_err(String::from); //加入失败时交还的节点任务在此释放
+            if push.compare_exchange(NODE_PUSHING, NODE_PUSHED, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+                continue; //已加入任务池
+            }
+
+            //加入时节点任务已被释放，则在任务池锁外结束节点
+            let e = match result {
+                Err(e) => e,
+                Ok(_) => format!("graph node dropped without running"),
+            };
+            GraphShared::complete(shared, id, Err(e));
+        }
+    }
+
+    //所有节点都已结束，则调用完成回调并唤醒等待者
+    fn check_done(&self) {
+        let callback = {
+            let mut state = self.state.lock().unwrap();
+            if state.finished < state.nodes.len() {
+                return;
+            }
+            match state.callback.take() {
+                Some(callback) => Some((callback, state.statuses())),
+                None => None,
+            }
+        };
+        self.cvar.notify_all();
+        if let Some((callback, statuses)) = callback {
+            callback(statuses);
+        }
+    }
+}
+
+/*
+* 任务图节点守护，节点任务执行结束、发生异常或未执行就被释放时，通知任务图节点结束
+*/
+struct NodeGuard {
+    shared: Arc<GraphShared>,                   //所属任务图
+    id:     usize,                              //节点id
+    push:   Arc<AtomicUsize>,                   //节点任务的加入状态
+    ran:    bool,                               //是否已开始执行
+    result: Option<GenResult<(), String>>,      //节点任务的执行结果
+}
+
+impl Drop for NodeGuard {
+    fn drop(&mut self) {
+        if self.ran {
+            //在执行节点任务的线程中结束节点，成功时加入后续节点
+            let result = match self.result.take() {
+                Some(r) => r,
+                None => Err(format!("graph node panic")),
+            };
+            GraphShared::complete(&self.shared, self.id, result);
+            return;
+        }
+
+        if self.push.compare_exchange(NODE_PUSHING, NODE_DROPPED, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+            return; //加入任务池时被释放，由加入者结束节点
+        }
+
+        //未执行就被释放，只记录节点失败并跳过后续节点，不会向任务池加入任务，任务池会将被移除的任务函数交给调用者在锁外释放
+        self.shared.fail(self.id, format!("graph node dropped without running"));
+        self.shared.check_done();
+    }
+}
+
+/*
+* 任务图句柄，用于查询和等待运行中的任务图
+*/
+#[derive(Clone)]
+pub struct GraphHandle {
+    shared: Arc<GraphShared>,   //任务图共享数据
+}
+
+impl Display for GraphHandle {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+        let state = self.shared.state.lock().unwrap();
+		write!(f, "GraphHandle[size = {}, finished = {}]", state.nodes.len(), state.finished)
+	}
+}
+
+impl GraphHandle {
+    //获取指定节点的状态
+    pub fn status(&self, id: usize) -> Option<NodeStatus> {
+        self.shared.state.lock().unwrap().nodes.get(id).map(|n| n.status.clone())
+    }
+
+    //获取所有节点的节点名和状态
+    pub fn statuses(&self) -> Vec<(Atom, NodeStatus)> {
+        self.shared.state.lock().unwrap().statuses()
+    }
+
+    //判断所有节点是否都已结束
+    pub fn is_done(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.finished == state.nodes.len()
+    }
+
+    //等待所有节点结束，返回所有节点的节点名和状态，在同一个任务池的任务中等待时，如果有独占任务在等待，未弹出的节点不会被弹出，会死锁
+    pub fn join(&self) -> Vec<(Atom, NodeStatus)> {
+        let mut state = self.shared.state.lock().unwrap();
+        while state.finished < state.nodes.len() {
+            state = self.shared.cvar.wait(state).unwrap();
+        }
+        state.statuses()
+    }
+
+    //等待所有节点结束，超时返回None
+    pub fn join_timeout(&self, timeout: Duration) -> Option<Vec<(Atom, NodeStatus)>> {
+        let time = Instant::now();
+        let mut state = self.shared.state.lock().unwrap();
+        while state.finished < state.nodes.len() {
+            let elapsed = time.elapsed();
+            if elapsed >= timeout {
+                return None;
+            }
+
+            let (s, _) = self.shared.cvar.wait_timeout(state, timeout - elapsed).unwrap();
+            state = s;
+        }
+        Some(state.statuses())
+    }
+}
@@ -0,0 +1,334 @@
+use std::thread;
+use std::boxed::FnBox;
+use std::time::{Instant, Duration};
+use std::sync::{Arc, Mutex, Condvar};
+use std::result::Result as GenResult;
+use std::fmt::{Display, Formatter, Result};
+use std::sync::atomic::{Ordering, AtomicUsize};
+
+use fnv::FnvHashMap;
+
+use pi_lib::atom::Atom;
+use task::TaskType;
+use task_pool::{TaskPool, push_task};
+
+/*
+* 成员任务的加入状态
+*/
+const MEMBER_PUSHING: usize = 0;    //正在加入任务池
+const MEMBER_PUSHED: usize = 1;     //已加入任务池
+const MEMBER_DROPPED: usize = 2;    //加入任务池时被释放
+
+/*
+* 任务组状态
+*/
+#[derive(Debug, Clone, Default)]
+pub struct GroupStatus {
+    pub total:      usize,  //加入的任务数量
+    pub completed:  usize,  //执行完成的任务数量
+    pub panicked:   usize,  //执行时发生异常的任务数量
+    pub cancelled:  usize,  //被取消或未执行就被释放的任务数量
+}
+
+impl GroupStatus {
+    //获取已结束的任务数量
+    pub fn finished(&self) -> usize {
+        self.completed + self.panicked + self.cancelled
+    }
+
+    //判断加入的任务是否都已结束
+    pub fn is_done(&self) -> bool {
+        self.finished() == self.total
+    }
+}
+
+/*
+* 任务组内部状态
+*/
+struct GroupState {
+    status:             GroupStatus,                                                        //任务组状态
+    counter:            usize,                                                              //成员编号计数器
+    members:            FnvHashMap<usize, Option<(Arc<(Mutex<TaskPool>, Condvar)>, usize)>>, //未结束的成员，成员编号对应任务池和任务唯一id，加入完成前为None
+    callback:           Option<Box<FnBox(GroupStatus) + Send>>,                             //完成回调
+    is_closed:          bool,                                                               //是否已关闭，关闭后不允许加入任务
+    is_cancel:          bool,                                                               //是否已取消，取消后不允许加入任务
+    cancel_on_panic:    bool,                                                               //成员执行时发生异常，是否取消任务组
+}
+
+/*
+* 任务组共享数据
+*/
+struct GroupShared {
+    state:  Mutex<GroupState>,  //任务组内部状态
+    cvar:   Condvar,            //等待任务组的条件变量
+}
+
+impl GroupShared {
+    //成员结束
+    fn finish(&self, seq: usize, ran: bool, panicked: bool) {
+        let (callback, is_cancel) = {
+            let mut state = self.state.lock().unwrap();
+            state.members.remove(&seq);
+            if !ran {
+                state.status.cancelled += 1;
+            } else if panicked {
+                state.status.panicked += 1;
+            } else {
+                state.status.completed += 1;
+            }
+            let is_cancel = panicked && state.cancel_on_panic && !state.is_cancel;
+            (take_callback(&mut state), is_cancel)
+        };
+        self.cvar.notify_all();
+
+        if is_cancel {
+            //成员执行时发生异常，则取消其它成员
+            self.cancel();
+        }
+        call(callback);
+    }
+
+    //取消所有等待中的成员，返回取消的成员数量
+    fn cancel(&self) -> usize {
+        let members: Vec<(Arc<(Mutex<TaskPool>, Condvar)>, usize)> = {
+            let mut state = self.state.lock().unwrap();
+            state.is_cancel = true;
+            state.members.values().filter_map(|m| m.clone()).collect()
+        };
+
+        let mut count = 0;
+        for (sync, uid) in members {
+            if cancel_member(&sync, uid) {
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+/*
+* 任务组成员守护，任务函数执行结束、发生异常或未执行就被释放时，通知任务组成员结束
+*/
+struct MemberGuard {
+    group:  Arc<GroupShared>,   //所属任务组
+    seq:    usize,              //成员编号
+    push:   Arc<AtomicUsize>,   //成员任务的加入状态
+    ran:    bool,               //是否已开始执行
+}
+
+impl Drop for MemberGuard {
+    fn drop(&mut self) {
+        if !self.ran && self.push.compare_exchange(MEMBER_PUSHING, MEMBER_DROPPED, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+            return; //加入任务池时被释放，由加入者结束成员
+        }
+
+        let panicked = self.ran && thread::panicking();
+        self.group.finish(self.seq, self.ran, panicked);
+    }
+}
+
+/*
+* 任务组，可以向任意任务池加入任务，统计成员的完成情况，等待所有成员结束，并可以取消所有等待中的成员
+*/
+#[derive(Clone)]
+pub struct TaskGroup {
+    shared: Arc<GroupShared>,   //任务组共享数据
+}
+
+impl Display for TaskGroup {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+        let state = self.shared.state.lock().unwrap();
+		write!(f, "TaskGroup[status = {:?}, member_size = {}, is_closed = {}, is_cancel = {}]",
+            state.status, state.members.len(), state.is_closed, state.is_cancel)
+	}
+}
+
+impl TaskGroup {
+    //构建一个任务组
+    pub fn new() -> Self {
+        TaskGroup {
+            shared: Arc::new(GroupShared {
+                state: Mutex::new(GroupState {
+                    status:             GroupStatus::default(),
+                    counter:            0,
+                    members:            FnvHashMap::default(),
+                    callback:           None,
+                    is_closed:          false,
+                    is_cancel:          false,
+                    cancel_on_panic:    false,
+                }),
+                cvar: Condvar::new(),
+            }),
+        }
+    }
+
+    //设置成员执行时发生异常，是否取消任务组
+    pub fn set_cancel_on_panic(&self, b: bool) {
+        self.shared.state.lock().unwrap().cancel_on_panic = b;
+    }
+
+    //设置完成回调，任务组关闭且所有成员结束后调用一次
+    pub fn set_callback(&self, callback: Box<FnBox(GroupStatus) + Send>) {
+        let callback = {
+            let mut state = self.shared.state.lock().unwrap();
+            state.callback = Some(callback);
+            take_callback(&mut state)
+        };
+        call(callback);
+    }
+
+    //获取任务组状态
+    pub fn status(&self) -> GroupStatus {
+        self.shared.state.lock().unwrap().status.clone()
+    }
+
+    //判断加入的任务是否都已结束
+    pub fn is_done(&self) -> bool {
+        self.shared.state.lock().unwrap().status.is_done()
+    }
+
+    //判断任务组是否已取消
+    pub fn is_cancel(&self) -> bool {
+        self.shared.state.lock().unwrap().is_cancel
+    }
+
+    //向指定任务池加入一个任务组成员，返回任务唯一id，任务组已关闭或已取消则返回错误
+    pub fn push(&self, sync: &Arc<(Mutex<TaskPool>, Condvar)>, task_type: TaskType, priority: u64, func: Box<FnBox() + Send>, info: Atom) -> GenResult<usize, String> {
+        let seq = {
+            let mut state = self.shared.state.lock().unwrap();
+            if state.is_closed || state.is_cancel {
+                return Err(format!("task group push failed, group closed or cancelled, info: {}", *info));
+            }
+            state.counter += 1;
+            state.status.total += 1;
+            let seq = state.counter;
+            state.members.insert(seq, None);
+            seq
+        };
+
+        let push = Arc::new(AtomicUsize::new(MEMBER_PUSHING));
+        let guard = MemberGuard {
+            group:  self.shared.clone(),
+            seq:    seq,
+            push:   push.clone(),
+            ran:    false,
+        };
+        let func = Box::new(move || {
+            let mut guard = guard;
+            guard.ran = true;
+            func();
+        });
+        let result = push_task(sync, task_type, move |task_pool| {
+            task_pool.push(task_type, priority, func, info)
+        }).map_err(String::from); //加入失败时交还的成员任务在此释放
+        match result {
+            Err(e) => {
+                //加入失败时成员已被释放，则在任务池锁外撤销成员
+                let callback = {
+                    let mut state = self.shared.state.lock().unwrap();
+                    state.members.remove(&seq);
+                    state.status.total -= 1;
+                    take_callback(&mut state)
+                };
+                self.shared.cvar.notify_all();
+                call(callback);
+                Err(e)
+            },
+            Ok(uid) => {
+                if push.compare_exchange(MEMBER_PUSHING, MEMBER_PUSHED, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+                    //加入后未执行就被释放，则在任务池锁外结束成员
+                    self.shared.finish(seq, false, false);
+                    return Ok(uid);
+                }
+
+                let is_cancel = {
+                    let mut state = self.shared.state.lock().unwrap();
+                    match state.members.get_mut(&seq) {
+                        Some(member) => *member = Some((sync.clone(), uid)),
+                        None => (), //成员已结束
+                    }
+                    state.is_cancel
+                };
+                if is_cancel {
+                    //加入时任务组已被取消，则立即取消成员
+                    cancel_member(sync, uid);
+                }
+                Ok(uid)
+            },
+        }
+    }
+
+    //关闭任务组，关闭后不允许加入任务，所有成员结束后调用完成回调
+    pub fn close(&self) {
+        let callback = {
+            let mut state = self.shared.state.lock().unwrap();
+            state.is_closed = true;
+            take_callback(&mut state)
+        };
+        call(callback);
+    }
+
+    //取消任务组，取消所有等待中的成员，正在执行的成员不受影响，返回取消的成员数量
+    pub fn cancel(&self) -> usize {
+        self.shared.cancel()
+    }
+
+    //等待加入的任务都结束，不等待完成回调，在同一个任务池的任务中等待时，如果有独占任务在等待，未弹出的任务不会被弹出，会死锁
+    pub fn join(&self) -> GroupStatus {
+        let mut state = self.shared.state.lock().unwrap();
+        while !state.status.is_done() {
+            state = self.shared.cvar.wait(state).unwrap();
+        }
+        state.status.clone()
+    }
+
+    //等待加入的任务都结束，超时返回None
+    pub fn join_timeout(&self, timeout: Duration) -> Option<GroupStatus> {
+        let time = Instant::now();
+        let mut state = self.shared.state.lock().unwrap();
+        while !state.status.is_done() {
+            let elapsed = time.elapsed();
+            if elapsed >= timeout {
+                return None;
+            }
+
+            let (s, _) = self.shared.cvar.wait_timeout(state, timeout - elapsed).unwrap();
+            state = s;
+        }
+        Some(state.status.clone())
+    }
+}
+
+//任务组已关闭且所有成员结束，则取出完成回调
+fn take_callback(state: &mut GroupState) -> Option<(Box<FnBox(GroupStatus) + Send>, GroupStatus)> {
+    if !state.is_closed || !state.status.is_done() {
+        return None;
+    }
+
+    match state.callback.take() {
+        Some(callback) => Some((callback, state.status.clone())),
+        None => None,
+    }
+}
+
+//调用完成回调
+fn call(callback: Option<(Box<FnBox(GroupStatus) + Send>, GroupStatus)>) {
+    if let Some((callback, status)) = callback {
+        callback(status);
+    }
+}
+
+//取消指定任务池中的成员，释放任务函数时成员计为取消
+fn cancel_member(sync: &Arc<(Mutex<TaskPool>, Condvar)>, uid: usize) -> bool {
+    let func = {
+        let &(ref lock, ref cvar) = &**sync;
+        let mut task_pool = lock.lock().unwrap();
+        let func = (*task_pool).cancel(uid);
+        if func.is_some() && (*task_pool).blocked_size() > 0 {
+            //唤醒等待任务池空间的调用者
+            cvar.notify_all();
+        }
+        func
+    };
+    func.is_some() //在任务池锁外释放任务函数
+}
@@ -0,0 +1,169 @@
+use std::mem;
+use std::any::Any;
+use std::time::{Instant, Duration};
+use std::sync::{Arc, Mutex, Condvar};
+use std::panic::{self, AssertUnwindSafe};
+use std::result::Result as GenResult;
+use std::fmt::{Display, Formatter, Result};
+
+use pi_lib::atom::Atom;
+use task::TaskType;
+use task_pool::{TaskPool, push_task};
+
+/*
+* 任务结果
+*/
+enum TaskResult<R> {
+    Pending,                    //任务未结束
+    Ready(GenResult<R, String>),//任务已结束，任务发生异常或未执行就被释放时为错误
+    Taken,                      //任务结果已被取出
+}
+
+/*
+* 任务结果槽
+*/
+struct ResultSlot<R> {
+    result: Mutex<TaskResult<R>>,   //任务结果
+    cvar:   Condvar,                //等待任务结果的条件变量
+}
+
+impl<R> ResultSlot<R> {
+    //设置任务结果，只有第一次设置有效
+    fn set(&self, r: GenResult<R, String>) {
+        let mut result = self.result.lock().unwrap();
+        if let TaskResult::Pending = *result {
+            *result = TaskResult::Ready(r);
+            self.cvar.notify_all();
+        }
+    }
+
+    //取出已结束任务的结果
+    fn take(result: &mut TaskResult<R>) -> Option<GenResult<R, String>> {
+        match mem::replace(result, TaskResult::Taken) {
+            TaskResult::Pending => {
+                *result = TaskResult::Pending;
+                None
+            },
+            TaskResult::Ready(r) => Some(r),
+            TaskResult::Taken => Some(Err(format!("task result already taken"))),
+        }
+    }
+}
+
+/*
+* 任务结果守护，任务未设置结果就被释放时，设置任务结果为错误
+*/
+struct ResultGuard<R> {
+    slot:   Arc<ResultSlot<R>>, //任务结果槽
+}
+
+impl<R> Drop for ResultGuard<R> {
+    fn drop(&mut self) {
+        self.slot.set(Err(format!("task dropped without running")));
+    }
+}
+
+/*
+* 任务句柄，用于获取任务函数的返回值
+*/
+pub struct TaskHandle<R> {
+    uid:    usize,              //任务唯一id，可用于取消任务
+    slot:   Arc<ResultSlot<R>>, //任务结果槽
+}
+
+impl<R> Display for TaskHandle<R> {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "TaskHandle[uid = {}, is_ready = {}]", self.uid, self.is_ready())
+	}
+}
+
+impl<R> TaskHandle<R> {
+    //获取任务唯一id
+    pub fn get_uid(&self) -> usize {
+        self.uid
+    }
+
+    //判断任务是否已结束
+    pub fn is_ready(&self) -> bool {
+        match *self.slot.result.lock().unwrap() {
+            TaskResult::Pending => false,
+            _ => true,
+        }
+    }
+
+    //尝试获取任务结果，任务未结束返回None
+    pub fn try_get(&self) -> Option<GenResult<R, String>> {
+        ResultSlot::take(&mut *self.slot.result.lock().unwrap())
+    }
+
+    //等待任务结果，在同一个任务池的任务中等待时，如果有独占任务在等待，被等待的任务不会被弹出，会死锁
+    pub fn wait(self) -> GenResult<R, String> {
+        let mut result = self.slot.result.lock().unwrap();
+        loop {
+            if let Some(r) = ResultSlot::take(&mut *result) {
+                return r;
+            }
+            result = self.slot.cvar.wait(result).unwrap();
+        }
+    }
+
+    //等待任务结果，超时返回None
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<GenResult<R, String>> {
+        let time = Instant::now();
+        let mut result = self.slot.result.lock().unwrap();
+        loop {
+            if let Some(r) = ResultSlot::take(&mut *result) {
+                return Some(r);
+            }
+
+            let elapsed = time.elapsed();
+            if elapsed >= timeout {
+                return None;
+            }
+            let (r, _) = self.slot.cvar.wait_timeout(result, timeout - elapsed).unwrap();
+            result = r;
+        }
+    }
+}
+
+/*
+* 线程安全的向指定任务池加入一个有返回值的任务，返回任务句柄，任务发生异常时任务结果为错误，异常会继续抛出给工作者
+*/
+pub fn spawn<R, F>(sync: &Arc<(Mutex<TaskPool>, Condvar)>, task_type: TaskType, priority: u64, func: F, info: Atom) -> GenResult<TaskHandle<R>, String>
+    where R: Send + 'static, F: FnOnce() -> R + Send + 'static {
+    let slot = Arc::new(ResultSlot {
+        result: Mutex::new(TaskResult::Pending),
+        cvar:   Condvar::new(),
+    });
+    let guard = ResultGuard {
+        slot:   slot.clone(),
+    };
+    let task = Box::new(move || {
+        let guard = guard;
+        match panic::catch_unwind(AssertUnwindSafe(func)) {
+            Ok(r) => guard.slot.set(Ok(r)),
+            Err(e) => {
+                guard.slot.set(Err(format!("task panic, e: {}", panic_message(&e))));
+                panic::resume_unwind(e);
+            },
+        }
+    });
+    let uid = push_task(sync, task_type, move |task_pool| {
+        task_pool.push(task_type, priority, task, info)
+    })?;
+    Ok(TaskHandle {
+        uid:    uid,
+        slot:   slot,
+    })
+}
+
+//获取异常信息
+fn panic_message(e: &Box<Any + Send>) -> String {
+    if let Some(s) = e.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = e.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        format!("unknown")
+    }
+}
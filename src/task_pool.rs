@@ -1,114 +1,261 @@
-use rand;
-use rand::Rng;
+use std::mem;
 use fnv::FnvHashMap;
+use std::sync::Arc;
 use std::boxed::FnBox;
-use std::collections::VecDeque;
-use std::fmt::{Display, Formatter, Result};
+use std::cmp::Reverse;
+use std::time::{Instant, Duration};
+use std::sync::{Mutex, Condvar};
+use std::collections::{VecDeque, BinaryHeap, BTreeMap};
+use std::result::Result as GenResult;
+use std::fmt::{Debug, Display, Formatter, Result};
 
 use pi_lib::atom::Atom;
 use task::{TaskType, Task, TaskCache};
+use weight_tree::WeightTree;
+use observer::{TaskObserver, TaskEvent, PoolObserver};
+use histogram::Histogram;
+use context;
+use policy::{SchedulingPolicy, WeightedRandomPolicy, PolicyRng};
+use trace::{TraceEvent, TraceQueue, TraceRecorder, TraceBatch};
+use worker::current_worker;
+
+/*
+* 同步任务队列
+*/
+struct SyncQueue<T> {
+    priority:   u64,                //队列优先级
+    boost:      u64,                //老化增加的优先级，队列为空时清零
+    queue:      VecDeque<Task<T>>,  //任务队列
+}
 
 /*
 * 同步任务池
 */
-struct SyncPool {
-    weight:         u64,                                //同步任务池权重
-    map:            FnvHashMap<u64, VecDeque<Task>>,    //同步任务队列表
-    delay_queue:    VecDeque<Task>,                     //延迟同步任务队列
+pub struct SyncPool<T> {
+    weight:         u64,                        //同步任务池权重
+    len:            u64,                        //同步任务数量
+    index:          FnvHashMap<u64, usize>,     //同步任务队列索引表，优先级对应同步任务队列的位置
+    queues:         Vec<SyncQueue<T>>,          //同步任务队列表
+    tree:           WeightTree,                 //同步任务队列权重树，同步任务队列的权重为老化后的优先级乘以任务数量
+    delay_queue:    VecDeque<Task<T>>,          //延迟同步任务队列
 }
 
-impl Display for SyncPool {
+impl<T> Display for SyncPool<T> {
 	fn fmt(&self, f: &mut Formatter) -> Result {
 		write!(f, "SyncPool[weight = {}, priority_size = {}, size = {}, delay_size = {}]", 
-        self.weight, self.map.len(), self.size(), self.delay_size())
+        self.weight, self.queues.len(), self.size(), self.delay_size())
 	}
 }
 
-impl SyncPool {
+impl<T> SyncPool<T> {
     //构建一个同步任务池
     fn new() -> Self {
         SyncPool {
             weight:         0,
-            map:            FnvHashMap::default(),
+            len:            0,
+            index:          FnvHashMap::default(),
+            queues:         Vec::new(),
+            tree:           WeightTree::new(),
             delay_queue:    VecDeque::new(),
         }
     }
 
+    //获取同步任务池权重
+    pub fn weight(&self) -> u64 {
+        self.weight
+    }
+
     //获取同步任务数量
-    fn size(&self) -> u64 {
-        let mut size: u64 = 0;
-        for val in self.map.values() {
-            size += val.len() as u64;
-        }
-        size
+    pub fn size(&self) -> u64 {
+        self.len
     }
 
     //获取延迟同步任务数量
-    fn delay_size(&self) -> u64 {
+    pub fn delay_size(&self) -> u64 {
         self.delay_queue.len() as u64
     }
 
-    //从同步任务队列中弹出任务
-    fn pop(&mut self, weight: u64, task: &mut Task) -> Option<Task> {
-        let mut reply = Option::None;
-        let mut w: i64 = weight as i64;
-        for (priority, queue) in self.map.iter_mut() {
-            w -= (priority * (queue.len() as u64)) as i64;
-            if w < 0 {
-                self.weight -= priority; //减少同步任务池权重
-                match queue.pop_front() {
-                    Some(t) => {
-                        //填充任务
-                        t.copy_to(task);
-                        reply = Some(t);
-                    },
-                    None => (),
+    //获取所有非空同步任务队列的优先级和权重
+    pub fn queues(&self) -> Vec<(u64, u64)> {
+        let mut vec = Vec::with_capacity(self.queues.len());
+        for (index, q) in self.queues.iter().enumerate() {
+            if q.queue.len() > 0 {
+                vec.push((q.priority, self.tree.get(index)));
+            }
+        }
+        vec
+    }
+
+    //按优先级加入的顺序遍历所有非空同步任务队列的优先级和权重，不分配内存
+    pub fn each_queue<F: FnMut(u64, u64)>(&self, mut f: F) {
+        for (index, q) in self.queues.iter().enumerate() {
+            if !q.queue.is_empty() {
+                f(q.priority, self.tree.get(index));
+            }
+        }
+    }
+
+    //判断指定优先级的同步任务队列是否为空
+    pub fn is_queue_empty(&self, priority: u64) -> bool {
+        match self.index.get(&priority) {
+            Some(index) => self.queues[*index].queue.is_empty(),
+            None => true,
+        }
+    }
+
+    //按指定权重从同步任务队列中弹出任务
+    pub fn pop(&mut self, weight: u64) -> Option<Task<T>> {
+        match self.tree.find(weight) {
+            Some(index) => self.pop_index(index),
+            None => None,
+        }
+    }
+
+    //从指定优先级的同步任务队列头弹出任务
+    pub fn pop_priority(&mut self, priority: u64) -> Option<Task<T>> {
+        let index = match self.index.get(&priority) {
+            Some(index) => *index,
+            None => return None,
+        };
+        self.pop_index(index)
+    }
+
+    //获取所有同步任务队列头中，指定键值最小的任务
+    pub fn peek_by_key<K: Ord, F: FnMut(&Task<T>) -> K>(&self, mut f: F) -> Option<&Task<T>> {
+        let mut reply: Option<(K, &Task<T>)> = None;
+        for q in self.queues.iter() {
+            if let Some(t) = q.queue.front() {
+                let key = f(t);
+                let is_min = match reply {
+                    Some((ref min, _)) => key < *min,
+                    None => true,
+                };
+                if is_min {
+                    reply = Some((key, t));
                 }
-                break;
             }
         }
-        reply
+        reply.map(|(_, t)| t)
+    }
+
+    //从所有同步任务队列头中，弹出指定键值最小的任务
+    pub fn pop_by_key<K: Ord, F: FnMut(&Task<T>) -> K>(&mut self, f: F) -> Option<Task<T>> {
+        let priority = match self.peek_by_key(f) {
+            Some(t) => t.get_aged_priority(),
+            None => return None,
+        };
+        self.pop_priority(priority)
     }
 
     //从同步延迟任务队列中弹出任务
-    fn delay_pop(&mut self, task: &mut Task) -> Option<Task> {
-        match self.delay_queue.pop_front() {
-            Some(t) => {
-                //填充任务
-                t.copy_to(task);
-                Some(t)
-            },
-            None => Option::None,
-        }
+    fn delay_pop(&mut self) -> Option<Task<T>> {
+        self.delay_queue.pop_front()
+    }
+
+    //从同步延迟任务队列尾弹出任务
+    fn delay_pop_back(&mut self) -> Option<Task<T>> {
+        self.delay_queue.pop_back()
     }
 
     //向同步任务队列尾加入任务
-    fn push_back(&mut self, task: Task) {
-        let priority = task.get_priority() as u64;
-        self.weight += priority;
-        self.map.entry(priority).or_insert(VecDeque::new()).push_back(task); //获取指定优先级的同步任务队列并加入任务，如果队列为空，则创建一个队列后再加入任务
+    fn push_back(&mut self, task: Task<T>) {
+        let index = self.queue_index(task.get_aged_priority());
+        self.queues[index].queue.push_back(task);
+        self.len += 1;
+        self.update(index);
     }
 
     //向同步任务队列头加入任务
-    fn push_front(&mut self, task: Task) {
-        let priority = task.get_priority() as u64;
-        self.weight += priority;
-        self.map.entry(priority).or_insert(VecDeque::new()).push_front(task); //获取指定优先级的同步任务队列并加入任务，如果队列为空，则创建一个队列后再加入任务
+    fn push_front(&mut self, task: Task<T>) {
+        let index = self.queue_index(task.get_aged_priority());
+        self.queues[index].queue.push_front(task);
+        self.len += 1;
+        self.update(index);
     }
 
     //向同步延迟任务队列尾加入任务
-    fn delay_push_back(&mut self, task: Task) {
+    fn delay_push_back(&mut self, task: Task<T>) {
         self.delay_queue.push_back(task);
     }
 
     //向同步延迟任务队列头加入任务
-    fn delay_push_front(&mut self, task: Task) {
+    fn delay_push_front(&mut self, task: Task<T>) {
         self.delay_queue.push_front(task);
     }
 
-    //移除指定优先级的同步任务队列
-    fn remove(&mut self, priority: u64) {
-       self.map.remove(&(priority as u64));
+    //移除指定唯一id的同步任务，包括同步延迟任务
+    fn cancel(&mut self, uid: usize) -> Option<Task<T>> {
+        for index in 0..self.queues.len() {
+            let pos = self.queues[index].queue.iter().position(|t| t.get_uid() == uid);
+            if let Some(i) = pos {
+                let reply = self.queues[index].queue.remove(i);
+                self.len -= 1;
+                self.update(index);
+                return reply;
+            }
+        }
+        match self.delay_queue.iter().position(|t| t.get_uid() == uid) {
+            Some(index) => self.delay_queue.remove(index),
+            None => None,
+        }
+    }
+
+    //移除指定优先级的同步任务队列，返回被移除的任务
+    fn remove(&mut self, priority: u64) -> Vec<Task<T>> {
+        let index = match self.index.get(&priority) {
+            Some(index) => *index,
+            None => return Vec::new(),
+        };
+        let tasks: Vec<Task<T>> = self.queues[index].queue.drain(..).collect();
+        self.len -= tasks.len() as u64;
+        self.update(index);
+        tasks
+    }
+
+    //移除同步任务队列中所有满足条件的任务，返回被移除的任务
+    fn take_by<F: FnMut(&Task<T>) -> bool>(&mut self, mut f: F) -> Vec<Task<T>> {
+        let mut tasks = Vec::new();
+        for index in 0..self.queues.len() {
+            if !self.queues[index].queue.iter().any(|t| f(t)) {
+                continue;
+            }
+
+            let queue: Vec<Task<T>> = self.queues[index].queue.drain(..).collect();
+            for t in queue {
+                if f(&t) {
+                    tasks.push(t);
+                } else {
+                    self.queues[index].queue.push_back(t);
+                }
+            }
+            self.update(index);
+        }
+        self.len -= tasks.len() as u64;
+        tasks
+    }
+
+    //移除同步延迟任务队列中所有满足条件的任务，返回被移除的任务
+    fn delay_take_by<F: FnMut(&Task<T>) -> bool>(&mut self, f: F) -> Vec<Task<T>> {
+        take_by(&mut self.delay_queue, f)
+    }
+
+    //老化所有非空同步任务队列，增加队列的优先级，老化后的优先级不超过上限，已超过上限的队列不老化
+    fn age(&mut self, increase: u64, cap: u64) {
+        for index in 0..self.queues.len() {
+            let priority = self.queues[index].priority;
+            let current = priority + self.queues[index].boost;
+            if self.queues[index].queue.is_empty() || current >= cap {
+                continue;
+            }
+
+            let aged = if current + increase > cap {
+                cap
+            } else {
+                current + increase
+            };
+            self.queues[index].boost = aged - priority;
+            self.update(index);
+        }
     }
 
     //移除同步延迟队列任务
@@ -118,108 +265,330 @@ impl SyncPool {
 
     //清空同步任务池
     fn clear(&mut self) {
-        self.map.clear();
+        self.weight = 0;
+        self.len = 0;
+        self.index.clear();
+        self.queues.clear();
+        self.tree.clear();
         self.delay_remove();
     }
+
+    //获取指定优先级的同步任务队列的位置，如果队列不存在，则创建一个队列
+    fn queue_index(&mut self, priority: u64) -> usize {
+        if let Some(index) = self.index.get(&priority) {
+            return *index;
+        }
+
+        let index = self.tree.push(0);
+        self.queues.push(SyncQueue {
+            priority:   priority,
+            boost:      0,
+            queue:      VecDeque::new(),
+        });
+        self.index.insert(priority, index);
+        index
+    }
+
+    //从指定位置的同步任务队列头弹出任务
+    fn pop_index(&mut self, index: usize) -> Option<Task<T>> {
+        let reply = self.queues[index].queue.pop_front();
+        if reply.is_some() {
+            self.len -= 1;
+            self.update(index);
+        }
+        reply
+    }
+
+    //指定位置的同步任务队列的任务数量或老化优先级改变了，更新权重
+    fn update(&mut self, index: usize) {
+        let len = self.queues[index].queue.len() as u64;
+        if len == 0 {
+            //队列为空，则清除老化
+            self.queues[index].boost = 0;
+        }
+        let weight = (self.queues[index].priority + self.queues[index].boost) * len;
+        let old = self.tree.get(index);
+        self.weight = self.weight + weight - old; //更新同步任务池权重
+        self.tree.set(index, weight);
+    }
+}
+
+/*
+* 异步任务池整理阈值，已弹出的任务位置超过此数量且超过一半时，整理异步任务队列
+*/
+const ASYNC_POOL_COMPACT_SIZE: usize = 64;
+
+/*
+* 异步任务桶，同一优先级的异步任务在同一个桶中，桶中的任务权重相同
+*/
+struct AsyncBucket {
+    priority:   u64,        //桶优先级
+    boost:      u64,                        //老化增加的优先级，桶为空时清零
+    slots:      Vec<usize>,                 //桶中任务在异步任务队列中的位置，无序
+    order:      BinaryHeap<Reverse<usize>>, //桶中任务在异步任务队列中的位置，最先加入的在堆顶，已移除的位置在访问或整理时清除
 }
 
 /*
 * 异步任务池
 */
-struct AsyncPool {
-    weight:         u64,            //异步任务队列权重
-    queue:          VecDeque<Task>, //异步任务队列
-    delay_queue:    VecDeque<Task>, //延迟异步任务队列
+pub struct AsyncPool<T> {
+    weight:         u64,                                //异步任务池权重
+    head:           usize,                              //异步任务队列中第一个可能有任务的位置
+    len:            usize,                              //异步任务数量
+    queue:          Vec<Option<Task<T>>>,               //异步任务队列，按加入顺序排列，被弹出的任务位置为None，整理时移除
+    slots:          Vec<(usize, usize)>,                //异步任务队列中各位置的任务所在的桶的位置和在桶中的位置
+    index:          FnvHashMap<u64, usize>,             //异步任务桶索引表，优先级对应异步任务桶的位置
+    buckets:        Vec<AsyncBucket>,                   //异步任务桶表
+    active:         BTreeMap<u64, usize>,               //非空异步任务桶表，优先级对应异步任务桶的位置
+    deadlines:      BinaryHeap<Reverse<(u64, usize)>>,  //异步任务的截止时间和在异步任务队列中的位置，截止时间最早的在堆顶，已移除的位置在访问或整理时清除
+    tree:           WeightTree,                         //异步任务桶权重树，桶的权重为老化后的优先级乘以任务数量
+    delay_queue:    VecDeque<Task<T>>,                  //延迟异步任务队列
 }
 
-impl Display for AsyncPool {
+impl<T> Display for AsyncPool<T> {
 	fn fmt(&self, f: &mut Formatter) -> Result {
-		write!(f, "AsyncPool[weight = {}, size = {}, delay_size = {}]", 
-        self.weight, self.size(), self.delay_size())
+		write!(f, "AsyncPool[weight = {}, priority_size = {}, size = {}, delay_size = {}]", 
+        self.weight, self.buckets.len(), self.size(), self.delay_size())
 	}
 }
 
-impl AsyncPool {
+impl<T> AsyncPool<T> {
     //构建一个同步任务池
     fn new() -> Self {
         AsyncPool {
             weight:         0,
-            queue:          VecDeque::new(),
+            head:           0,
+            len:            0,
+            queue:          Vec::new(),
+            slots:          Vec::new(),
+            index:          FnvHashMap::default(),
+            buckets:        Vec::new(),
+            active:         BTreeMap::new(),
+            deadlines:      BinaryHeap::new(),
+            tree:           WeightTree::new(),
             delay_queue:    VecDeque::new(),
         }
     }
 
+    //获取异步任务池权重
+    pub fn weight(&self) -> u64 {
+        self.weight
+    }
+
     //获取异步任务数量
-    fn size(&self) -> u64 {
-        self.queue.len() as u64
+    pub fn size(&self) -> u64 {
+        self.len as u64
     }
 
     //获取延迟异步任务数量
-    fn delay_size(&self) -> u64 {
+    pub fn delay_size(&self) -> u64 {
         self.delay_queue.len() as u64
     }
 
-    //从异步任务队列中弹出任务
-    fn pop(&mut self, mut weight: u64, task: &mut Task) -> Option<Task> {
-        let mut index = -1;
-        let mut priority = 0;
-        let mut reply = Option::None;
-        for i in 0..self.queue.len() {
-            match self.queue.get_mut(i) {
-                Some(t) => {
-                    priority = t.get_priority();
-                    if weight < (priority as u64) {
-                        //已选中异步任务
-                        index = i as isize;
-                        break;
-                    }
-                    //没有选中，则减少权重继续查找下一个任务
-                    weight -= priority as u64;
-                },
-                None => continue,
+    //按指定权重从异步任务队列中弹出任务
+    pub fn pop(&mut self, weight: u64) -> Option<Task<T>> {
+        let index = match self.tree.find(weight) {
+            Some(index) => index,
+            None => return None,
+        };
+
+        //桶中的任务权重相同，按权重在桶中的偏移选择任务
+        let bucket = &self.buckets[index];
+        let offset = (weight - self.tree.prefix(index)) / (bucket.priority + bucket.boost).max(1);
+        let slot = if offset as usize >= bucket.slots.len() {
+            bucket.slots.len() - 1
+        } else {
+            offset as usize
+        };
+        let pos = bucket.slots[slot];
+        self.pop_index(pos)
+    }
+
+    //获取异步任务队列头的任务，即最先加入的任务
+    pub fn peek_front(&self) -> Option<&Task<T>> {
+        self.queue[self.head..].iter().filter_map(|t| t.as_ref()).next()
+    }
+
+    //从异步任务队列头弹出任务
+    pub fn pop_front(&mut self) -> Option<Task<T>> {
+        while self.head < self.queue.len() {
+            if self.queue[self.head].is_some() {
+                let index = self.head;
+                return self.pop_index(index);
             }
+            self.head += 1;
         }
-        if index > -1 {
-            self.weight -= priority as u64; //减少异步任务池权重
-            match self.queue.remove(index as usize) {
-                Some(t) => {
-                    //填充任务
-                    t.copy_to(task);
-                    reply = Some(t);
-                },
-                None => (),
+        None
+    }
+
+    //获取非空异步任务桶的最高优先级，不包括老化增加的优先级
+    pub fn highest_priority(&self) -> Option<u64> {
+        self.active.keys().next_back().cloned()
+    }
+
+    //从优先级最高的非空异步任务桶中弹出最先加入的任务，不包括老化增加的优先级
+    pub fn pop_highest(&mut self) -> Option<Task<T>> {
+        let index = match self.active.values().next_back() {
+            Some(index) => *index,
+            None => return None,
+        };
+        while let Some(Reverse(pos)) = self.buckets[index].order.pop() {
+            if self.queue[pos].is_some() {
+                return self.pop_index(pos);
             }
         }
-        reply
+        None
     }
 
-    //从异步延迟任务队列中弹出任务
-    fn delay_pop(&mut self, task: &mut Task) -> Option<Task> {
-        match self.delay_queue.pop_front() {
-            Some(t) => {
-                //填充任务
-                t.copy_to(task);
-                Some(t)
-            },
-            None => Option::None,
+    //获取异步任务队列中最早的截止时间
+    pub fn earliest_deadline(&mut self) -> Option<u64> {
+        while let Some(&Reverse((deadline, pos))) = self.deadlines.peek() {
+            if self.queue[pos].is_some() {
+                return Some(deadline);
+            }
+            self.deadlines.pop(); //已移除的任务
+        }
+        None
+    }
+
+    //从异步任务队列中弹出截止时间最早的任务，截止时间相同则弹出最先加入的任务
+    pub fn pop_earliest_deadline(&mut self) -> Option<Task<T>> {
+        while let Some(Reverse((_, pos))) = self.deadlines.pop() {
+            if self.queue[pos].is_some() {
+                return self.pop_index(pos);
+            }
+        }
+        None
+    }
+
+    //获取异步任务队列中，指定键值最小的任务，键值相同则取最先加入的任务
+    pub fn peek_by_key<K: Ord, F: FnMut(&Task<T>) -> K>(&self, mut f: F) -> Option<&Task<T>> {
+        self.queue[self.head..].iter().filter_map(|t| t.as_ref()).min_by_key(|t| f(t))
+    }
+
+    //从异步任务队列中，弹出指定键值最小的任务，键值相同则弹出最先加入的任务
+    pub fn pop_by_key<K: Ord, F: FnMut(&Task<T>) -> K>(&mut self, mut f: F) -> Option<Task<T>> {
+        let mut index: Option<(usize, K)> = None;
+        for i in self.head..self.queue.len() {
+            if let Some(ref t) = self.queue[i] {
+                let key = f(t);
+                let is_min = match index {
+                    Some((_, ref min)) => key < *min,
+                    None => true,
+                };
+                if is_min {
+                    index = Some((i, key));
+                }
+            }
+        }
+        match index {
+            Some((i, _)) => self.pop_index(i),
+            None => None,
         }
     }
 
+    //从异步延迟任务队列中弹出任务
+    fn delay_pop(&mut self) -> Option<Task<T>> {
+        self.delay_queue.pop_front()
+    }
+
+    //从异步延迟任务队列尾弹出任务
+    fn delay_pop_back(&mut self) -> Option<Task<T>> {
+        self.delay_queue.pop_back()
+    }
+
     //向异步任务队列尾加入任务
-    fn push_back(&mut self, task: Task) {
-        let priority = task.get_priority() as u64;
-        self.weight += priority;
-        self.queue.push_back(task);
+    fn push_back(&mut self, task: Task<T>) {
+        let index = self.bucket_index(task.get_aged_priority());
+        let pos = self.queue.len();
+        self.slots.push((index, self.buckets[index].slots.len()));
+        self.buckets[index].slots.push(pos);
+        self.buckets[index].order.push(Reverse(pos));
+        self.deadlines.push(Reverse((task.get_deadline(), pos)));
+        self.queue.push(Some(task));
+        self.len += 1;
+        self.update(index);
     }
 
     //向异步延迟任务队列尾加入任务
-    fn delay_push_back(&mut self, task: Task) {
+    fn delay_push_back(&mut self, task: Task<T>) {
         self.delay_queue.push_back(task);
     }
 
+    //移除指定唯一id的异步任务，包括异步延迟任务
+    fn cancel(&mut self, uid: usize) -> Option<Task<T>> {
+        let mut index = None;
+        for i in self.head..self.queue.len() {
+            if let Some(ref t) = self.queue[i] {
+                if t.get_uid() == uid {
+                    index = Some(i);
+                    break;
+                }
+            }
+        }
+        if let Some(i) = index {
+            return self.pop_index(i);
+        }
+        match self.delay_queue.iter().position(|t| t.get_uid() == uid) {
+            Some(index) => self.delay_queue.remove(index),
+            None => None,
+        }
+    }
+
+    //移除异步任务队列中所有满足条件的任务，返回被移除的任务
+    fn take_by<F: FnMut(&Task<T>) -> bool>(&mut self, mut f: F) -> Vec<Task<T>> {
+        let mut tasks = Vec::new();
+        for index in self.head..self.queue.len() {
+            let is_take = match self.queue[index] {
+                Some(ref t) => f(t),
+                None => false,
+            };
+            if is_take {
+                if let Some(t) = self.remove_index(index) {
+                    tasks.push(t);
+                }
+            }
+        }
+        self.compact();
+        tasks
+    }
+
+    //移除异步延迟任务队列中所有满足条件的任务，返回被移除的任务
+    fn delay_take_by<F: FnMut(&Task<T>) -> bool>(&mut self, f: F) -> Vec<Task<T>> {
+        take_by(&mut self.delay_queue, f)
+    }
+
+    //老化所有非空异步任务桶，增加桶的优先级，老化后的优先级不超过上限，已超过上限的桶不老化
+    fn age(&mut self, increase: u64, cap: u64) {
+        for index in 0..self.buckets.len() {
+            let priority = self.buckets[index].priority;
+            let current = priority + self.buckets[index].boost;
+            if self.buckets[index].slots.is_empty() || current >= cap {
+                continue;
+            }
+
+            let aged = if current + increase > cap {
+                cap
+            } else {
+                current + increase
+            };
+            self.buckets[index].boost = aged - priority;
+            self.update(index);
+        }
+    }
+
     //移除异步任务队列
     pub fn remove(&mut self) {
-       self.queue.clear();
+        self.weight = 0;
+        self.head = 0;
+        self.len = 0;
+        self.queue.clear();
+        self.slots.clear();
+        self.index.clear();
+        self.buckets.clear();
+        self.active.clear();
+        self.deadlines.clear();
+        self.tree.clear();
     }
 
     //移除异步延迟队列任务
@@ -232,146 +601,1738 @@ impl AsyncPool {
         self.remove();
         self.delay_remove();
     }
+
+    //获取指定优先级的异步任务桶的位置，如果桶不存在，则创建一个桶
+    fn bucket_index(&mut self, priority: u64) -> usize {
+        if let Some(index) = self.index.get(&priority) {
+            return *index;
+        }
+
+        let index = self.tree.push(0);
+        self.buckets.push(AsyncBucket {
+            priority:   priority,
+            boost:      0,
+            slots:      Vec::new(),
+            order:      BinaryHeap::new(),
+        });
+        self.index.insert(priority, index);
+        index
+    }
+
+    //弹出指定位置的异步任务
+    fn pop_index(&mut self, pos: usize) -> Option<Task<T>> {
+        let reply = self.remove_index(pos);
+        self.compact();
+        reply
+    }
+
+    //移除指定位置的异步任务，并从所在的桶中移除，不整理异步任务队列
+    fn remove_index(&mut self, pos: usize) -> Option<Task<T>> {
+        let reply = self.queue[pos].take();
+        if reply.is_some() {
+            let (index, slot) = self.slots[pos];
+            self.buckets[index].slots.swap_remove(slot);
+            if let Some(&moved) = self.buckets[index].slots.get(slot) {
+                self.slots[moved].1 = slot; //桶中最后一个任务移到了被移除任务的位置
+            }
+            self.len -= 1;
+            self.update(index);
+        }
+        reply
+    }
+
+    //指定位置的异步任务桶的任务数量或老化优先级改变了，更新权重
+    fn update(&mut self, index: usize) {
+        let len = self.buckets[index].slots.len() as u64;
+        if len == 0 {
+            //桶为空，则清除老化
+            self.buckets[index].boost = 0;
+            self.active.remove(&self.buckets[index].priority);
+        } else {
+            self.active.insert(self.buckets[index].priority, index);
+        }
+        let weight = (self.buckets[index].priority + self.buckets[index].boost) * len;
+        let old = self.tree.get(index);
+        self.weight = self.weight + weight - old; //更新异步任务池权重
+        self.tree.set(index, weight);
+    }
+
+    //已弹出的任务位置过多时，移除已弹出的任务位置，并重建各桶中任务的位置和截止时间堆，桶的权重不变
+    fn compact(&mut self) {
+        let free = self.queue.len() - self.len;
+        if free < ASYNC_POOL_COMPACT_SIZE || free * 2 < self.queue.len() {
+            return;
+        }
+
+        let queue = mem::replace(&mut self.queue, Vec::with_capacity(self.len));
+        let slots = mem::replace(&mut self.slots, Vec::with_capacity(self.len));
+        for bucket in self.buckets.iter_mut() {
+            bucket.slots.clear();
+            bucket.order.clear();
+        }
+        self.deadlines.clear();
+        for (t, (index, _)) in queue.into_iter().zip(slots) {
+            if let Some(t) = t {
+                let pos = self.queue.len();
+                self.slots.push((index, self.buckets[index].slots.len()));
+                self.buckets[index].slots.push(pos);
+                self.buckets[index].order.push(Reverse(pos));
+                self.deadlines.push(Reverse((t.get_deadline(), pos)));
+                self.queue.push(Some(t));
+            }
+        }
+        self.head = 0;
+    }
 }
 
 /*
-* 任务池
+* 任务池满时的处理策略
+*/
+#[derive(Debug, Copy, Clone)]
+pub enum FullPolicy {
+    Reject,             //拒绝加入，并返回错误
+    Block(Duration),    //阻塞调用者，直到任务池有空间或超时
+    EvictDelay,         //驱逐延迟任务队列尾的任务，没有可驱逐的任务则拒绝加入
+}
+
+/*
+* 任务池满时的处理统计
+*/
+#[derive(Debug, Clone, Default)]
+pub struct FullStatistics {
+    pub rejected:   u64,    //拒绝加入的次数，包括阻塞超时
+    pub blocked:    u64,    //阻塞调用者的次数
+    pub timeout:    u64,    //阻塞超时的次数
+    pub evicted:    u64,    //驱逐延迟任务的次数
+}
+
+/*
+* 加入任务失败的原因
 */
-pub struct TaskPool {
-    task_cache:     TaskCache,  //任务缓存
-    sync_pool:      SyncPool,   //同步任务池
-    async_pool:     AsyncPool,  //异步任务池
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PushErrorKind {
+    Draining = 0,   //任务池处于排空模式
+    Full,           //任务池已满
+    Timeout,        //任务池已满，阻塞等待超时
 }
 
-unsafe impl Sync for TaskPool {}
+/*
+* 加入任务失败的错误，包括失败原因和未加入任务池的任务负载，任务负载交还给调用者，由调用者在任务池锁外释放或重新投递
+*/
+pub struct PushError<T> {
+    kind:       PushErrorKind,  //失败原因的类型
+    reason:     String,         //失败原因
+    payload:    T,              //未加入任务池的任务负载
+}
 
-impl Display for TaskPool {
+impl<T> Display for PushError<T> {
 	fn fmt(&self, f: &mut Formatter) -> Result {
-		write!(f, "TaskPool[cache_size = {}, sync_pool = {}, async_pool = {}]", 
-            self.task_cache.size(), self.sync_pool, self.async_pool)
+		write!(f, "{}", self.reason)
 	}
 }
 
-impl TaskPool {
-    //构建一个任务池
-    pub fn new(len: u32) -> Self {
-        TaskPool {
-            task_cache: TaskCache::new(len),
-            sync_pool:  SyncPool::new(),
-            async_pool: AsyncPool::new(),
-        }
+impl<T> Debug for PushError<T> {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "PushError[kind = {:?}, reason = {:?}]", self.kind, self.reason)
+	}
+}
+
+impl<T> From<PushError<T>> for String {
+    fn from(err: PushError<T>) -> Self {
+        err.reason
     }
+}
 
-    //获取任务数量
-    pub fn size(&self) -> u64 {
-        self.sync_pool.size() + self.sync_pool.delay_size() + self.async_pool.size() + self.async_pool.delay_size()
-    }
-
-    //从任务池中弹出一个任务
-    pub fn pop(&mut self, task: &mut Task) {
-        let mut wait_free: Option<Task>;
-        let mut r: u64;
-        let mut sw = self.sync_pool.weight;
-        let mut aw = self.async_pool.weight;
-        let w = sw + aw;
-        if w > 0 {
-            //判断从同步还是异步任务队列中弹出
-            r = rand::thread_rng().gen_range(0, w);
-            if r < sw {
-                //从同步任务队列中弹出
-                wait_free=self.sync_pool.pop(r, task);
-                self.free(wait_free);
-            } else {
-                //从异步任务队列中弹出
-                wait_free=self.async_pool.pop(r - sw, task);
-                self.free(wait_free);
-            }
-        }
-        sw = self.sync_pool.delay_size();
-        aw = self.async_pool.delay_size();
-        if sw > 0 {
-            if aw > 0 {
-                //判断从同步还是异步延迟任务队列中弹出
-                r = rand::thread_rng().gen_range(0, w);
-                if r < sw {
-                    //从同步延迟任务队列中弹出
-                    wait_free=self.sync_pool.delay_pop(task);
-                    self.free(wait_free);
-                } else {
-                    //从异步延迟任务队列中弹出
-                    wait_free=self.async_pool.delay_pop(task);
-                    self.free(wait_free);
-                }
-            } else {
-                //只有从同步延迟任务队列中弹出
-                wait_free=self.sync_pool.delay_pop(task);
-                self.free(wait_free);
-            }
-        } else if aw > 0 {
-            //只有从异步延迟任务队列中弹出
-            wait_free=self.async_pool.delay_pop(task);
-            self.free(wait_free);
+impl<T> PushError<T> {
+    //构建一个加入任务失败的错误
+    pub fn new(kind: PushErrorKind, reason: String, payload: T) -> Self {
+        PushError {
+            kind: kind,
+            reason: reason,
+            payload: payload,
         }
     }
 
-    //向任务池加入一个任务
-    pub fn push(&mut self, task_type: TaskType, priority: u64, func: Box<FnBox()>, info: Atom) {
-        let mut task: Task = self.task_cache.pop();
-        task.set_priority(priority);
-        task.set_func(Some(func));
-        task.set_info(info);
-        if priority > 0 {
-            match task_type {
-                TaskType::Async => {
-                    //加入异步任务队列
-                    self.async_pool.push_back(task);
-                },
-                TaskType::Sync => {
-                    //加入同步任务队列尾
-                    self.sync_pool.push_back(task);
-                },
-                TaskType::SyncImme => {
-                    //加入同步任务队列头
-                    self.sync_pool.push_front(task);
-                },
-                _ => (),
-            }
-        } else {
-            //加入延迟任务队列
-            match task_type {
-                TaskType::Async => {
-                    //加入异步延迟任务队列
-                    self.async_pool.delay_push_back(task);
-                },
-                TaskType::Sync => {
-                    //加入同步延迟任务队列尾
-                    self.sync_pool.delay_push_back(task);
-                },
-                TaskType::SyncImme => {
-                    //加入同步延迟任务队列头
-                    self.sync_pool.delay_push_front(task);
-                },
-                _ => (),
-            }
+    //获取失败原因的类型
+    pub fn kind(&self) -> PushErrorKind {
+        self.kind
+    }
+
+    //获取失败原因
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    //取出未加入任务池的任务负载
+    pub fn into_payload(self) -> T {
+        self.payload
+    }
+}
+
+/*
+* 任务老化配置，默认不开启，每经过一个老化周期，非空的同步任务队列和异步任务桶增加指定的优先级，直到上限，队列或桶为空时清除老化，
+* 老化只影响加权选择时的权重，即只对加权随机和加权轮询调度策略有效，不修改任务的优先级，延迟任务老化后的优先级不小于1时，以老化后的优先级加入同步或异步任务队列，
+* 任务的原始优先级保持不变，老化增加的优先级记录在任务中
+*/
+#[derive(Debug, Copy, Clone)]
+pub struct AgingConfig {
+    pub interval:   Duration,           //老化周期
+    pub rate:       u64,                //每个老化周期增加的优先级
+    pub cap:        u64,                //老化后的优先级上限，优先级已超过上限的任务不老化
+    pub max_wait:   Option<Duration>,   //任务的最大等待时间，在下一个老化周期检查所有等待中的任务，超过的任务被优先弹出，None表示不限制
+}
+
+impl AgingConfig {
+    //构建一个任务老化配置，不限制最大等待时间
+    pub fn new(interval: Duration, rate: u64, cap: u64) -> Self {
+        AgingConfig {
+            interval:   interval,
+            rate:       rate,
+            cap:        cap,
+            max_wait:   None,
         }
     }
 
-    //移除指定优先级的同步任务
-    pub fn remove_sync_task(&mut self, priority: u64) {
-        self.sync_pool.remove(priority);
+    //设置任务的最大等待时间
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = Some(max_wait);
+        self
     }
+}
 
-    //清空所有任务
-    pub fn clear(&mut self) {
-        self.async_pool.clear();
-        self.sync_pool.clear();
+/*
+* 令牌桶，用于限制一类任务的执行速率
+*/
+struct TokenBucket<T> {
+    limit:  Option<(u64, u64)>,             //每秒生成的令牌数和最多保存的令牌数，None表示已移除限制
+    tokens: u64,                            //当前令牌数，单位为百万分之一个令牌
+    time:   Instant,                        //上次生成令牌的时间
+    queue:  VecDeque<Task<T>>,              //因没有令牌而等待的任务
+}
+
+impl<T> TokenBucket<T> {
+    //构建一个令牌桶，初始令牌数为最多保存的令牌数
+    fn new(rate: u64, burst: u64, now: Instant) -> Self {
+        TokenBucket {
+            limit:  Some((rate, burst)),
+            tokens: burst * TOKEN_UNIT,
+            time:   now,
+            queue:  VecDeque::new(),
+        }
     }
 
-    //释放指定任务
-    fn free(&mut self, task: Option<Task>) {
-        match task {
-            Some(t) => self.task_cache.push(t),
-            None => (),
+    //生成令牌
+    fn refill(&mut self, now: Instant) {
+        if let Some((rate, burst)) = self.limit {
+            if now > self.time {
+                let elapsed = now.duration_since(self.time);
+                let micros = elapsed.as_secs() * 1000000 + elapsed.subsec_micros() as u64;
+                let max = burst * TOKEN_UNIT;
+                let tokens = self.tokens.saturating_add(micros.saturating_mul(rate));
+                self.tokens = if tokens > max { max } else { tokens };
+                self.time = now;
+            }
         }
     }
+
+    //获取下一个令牌生成的时间，已移除限制或有令牌则返回上次生成令牌的时间，不再生成令牌返回None
+    fn ready_time(&self) -> Option<Instant> {
+        match self.limit {
+            Some((rate, _)) if self.tokens < TOKEN_UNIT => {
+                if rate == 0 {
+                    return None;
+                }
+                let lack = TOKEN_UNIT - self.tokens;
+                let micros = if lack % rate == 0 { lack / rate } else { lack / rate + 1 };
+                Some(self.time + Duration::from_micros(micros))
+            },
+            _ => Some(self.time),
+        }
+    }
+
+    //获取一个令牌，没有令牌返回false
+    fn acquire(&mut self, now: Instant) -> bool {
+        if self.limit.is_none() {
+            return true;
+        }
+
+        self.refill(now);
+        if self.tokens < TOKEN_UNIT {
+            return false;
+        }
+        self.tokens -= TOKEN_UNIT;
+        true
+    }
+}
+
+/*
+* 过载丢弃状态
+*/
+struct ShedState {
+    threshold:  Duration,                                       //等待时长的99分位数阈值，超过则任务池过载
+    window:     Duration,                                       //统计窗口，每个窗口结束时判断下一个窗口是否过载
+    callback:   ShedCallback,                                   //丢弃任务的回调
+    recent:     Histogram,                                      //当前窗口的等待时长直方图
+    time:       Instant,                                        //当前窗口的开始时间
+    overload:   bool,                                           //是否过载
+}
+
+/*
+* 丢弃任务的回调，参数为任务唯一id、任务信息和已等待时长
+*/
+pub type ShedCallback = Arc<Fn(usize, Atom, Duration) + Send + Sync>;
+
+/*
+* 被丢弃或驱逐的任务和未写入的调度轨迹，由调用者在任务池锁外释放，释放时先释放任务负载，再调用丢弃任务的回调，最后写入调度轨迹
+*/
+pub struct Released<T> {
+    payloads:   Vec<T>,                                 //被丢弃或驱逐的任务负载
+    shed:       Vec<(ShedCallback, usize, Atom, Duration)>, //被丢弃任务的回调和回调参数
+    trace:      Option<TraceBatch>,                     //未写入的调度轨迹批次
+}
+
+impl<T> Drop for Released<T> {
+    fn drop(&mut self) {
+        self.payloads.clear();
+        for (callback, uid, info, wait) in self.shed.drain(..) {
+            callback(uid, info, wait);
+        }
+        self.trace.take(); //写入调度轨迹
+    }
+}
+
+impl<T> Released<T> {
+    //获取被丢弃或驱逐的任务负载数量
+    pub fn len(&self) -> usize {
+        self.payloads.len()
+    }
+
+    //判断是否没有被丢弃或驱逐的任务负载
+    pub fn is_empty(&self) -> bool {
+        self.payloads.is_empty()
+    }
+}
+
+/*
+* 等待任务池空闲时单次等待的最长时间，单位ms
+*/
+const IDLE_WAIT_TIMEOUT: u64 = 10;
+
+/*
+* 定时任务执行时间堆的重建阈值，超过此数量且超过定时任务数量的两倍时，移除失效的时间并重建
+*/
+const TIMED_SHRINK_SIZE: usize = 64;
+
+/*
+* 令牌单位，令牌数以百万分之一个令牌计算
+*/
+const TOKEN_UNIT: u64 = 1000000;
+
+/*
+* 任务池
+*/
+pub struct TaskPool<T = Box<FnBox() + Send>> {
+    counter:        usize,                                          //任务唯一id计数器
+    task_cache:     TaskCache<T>,                                   //任务缓存
+    sync_pool:      SyncPool<T>,                                    //同步任务池
+    async_pool:     AsyncPool<T>,                                   //异步任务池
+    policy:         Box<SchedulingPolicy<T>>,                       //调度策略
+    serial:         FnvHashMap<Atom, VecDeque<(TaskType, Task<T>)>>, //串行任务表，键存在表示该键有任务在队列中或正在执行，值为等待的串行任务
+    timed:          BinaryHeap<Reverse<(Instant, usize)>>,          //定时任务的执行时间堆，任务被取消后延迟移除，失效的过多时重建
+    timed_tasks:    FnvHashMap<usize, (TaskType, Task<T>)>,         //定时任务表，到达执行时间后加入任务队列
+    capacity:       u64,                                            //任务池容量，0表示不限制
+    sync_capacity:  u64,                                            //同步任务容量，包括同步延迟任务，0表示不限制
+    async_capacity: u64,                                            //异步任务容量，包括异步延迟任务，0表示不限制
+    full_policy:    FullPolicy,                                     //任务池满时的处理策略
+    full_stat:      FullStatistics,                                 //任务池满时的处理统计
+    blocked:        usize,                                          //正在等待任务池空间的调用者数量
+    aging:          Option<AgingConfig>,                            //任务老化配置，None表示不老化
+    aged:           Instant,                                        //上次老化的时间
+    clock:          Option<Instant>,                                //手动时钟的当前时间，None表示使用系统时钟
+    skew:           Duration,                                       //关闭手动时钟后任务池时间超前系统时钟的时长，保证任务池时间不会倒退
+    overdue:        VecDeque<(TaskType, Task<T>)>,                  //超过最大等待时间的任务，按等待时间从长到短排列，优先弹出
+    limits:         FnvHashMap<Atom, TokenBucket<T>>,               //任务类别的令牌桶表，任务类别为任务信息
+    throttled:      (u64, u64),                                     //因没有令牌而等待的同步和异步任务数量
+    classes:        VecDeque<Atom>,                                 //有等待任务的任务类别，按轮询顺序排列，等待任务被移除后延迟移除
+    drain:          Option<u64>,                                    //排空模式下保留任务的最小优先级，None表示未进入排空模式
+    running:        u64,                                            //已弹出但未完成的任务数量
+    workers:        usize,                                          //在任务池上工作的工作者数量
+    observer:       Option<PoolObserver>,                           //任务生命周期观察者
+    type_waits:     Vec<Histogram>,                                 //各任务类型的等待时长直方图
+    priority_waits: FnvHashMap<u64, Histogram>,                     //各优先级的等待时长直方图
+    shedding:       Option<ShedState>,                              //过载丢弃状态，None表示不丢弃
+    shed:           u64,                                            //过载时丢弃的任务数量
+    rng:            PolicyRng,                                      //调度随机数生成器
+    recorder:       Option<TraceRecorder>,                          //调度轨迹记录器，None表示不记录
+    exclusive:      VecDeque<Task<T>>,                              //等待中的独占任务
+    is_exclusive:   bool,                                           //是否有独占任务正在执行
+    released:       Vec<T>,                                         //被丢弃或驱逐的任务负载，由调用者在任务池锁外释放
+    shed_calls:     Vec<(ShedCallback, usize, Atom, Duration)>,     //被丢弃任务的回调和回调参数，由调用者在任务池锁外调用
+}
+
+impl<T> Display for TaskPool<T> {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "TaskPool[counter = {}, cache_size = {}, sync_pool = {}, async_pool = {}, serial_size = {}, timed_size = {}, capacity = {}, full_policy = {:?}, full_stat = {:?}, aging = {:?}, overdue_size = {}, limit_size = {}, throttled_size = {}, drain = {:?}, running = {}, observer = {:?}, is_overload = {}, shed = {}, rng = {}, is_record = {}, exclusive_size = {}, is_exclusive = {}, is_manual_clock = {}]", 
+            self.counter, self.task_cache.size(), self.sync_pool, self.async_pool, self.serial_size(), self.timed_size(), self.capacity, self.full_policy, self.full_stat, self.aging, self.overdue.len(), self.limits.len(), self.throttled_size(), self.drain, self.running, self.observer, self.is_overload(), self.shed, self.rng, self.is_record(), self.exclusive.len(), self.is_exclusive, self.is_manual_clock())
+	}
+}
+
+impl TaskPool {
+    //构建一个任务池，默认使用加权随机调度策略
+    pub fn new(len: u32) -> Self {
+        TaskPool::with_policy(len, Box::new(WeightedRandomPolicy::new()))
+    }
+
+    //构建一个使用指定调度策略的任务池
+    pub fn with_policy(len: u32, policy: Box<SchedulingPolicy>) -> Self {
+        TaskPool::with_payload(len, policy)
+    }
+}
+
+impl<T> TaskPool<T> {
+    //构建一个使用指定调度策略、负载为指定类型的任务池，可用于带优先级的消息队列，由专门的消费者弹出任务并取出负载
+    pub fn with_payload(len: u32, policy: Box<SchedulingPolicy<T>>) -> Self {
+        TaskPool {
+            counter:        0,
+            task_cache:     TaskCache::new(len),
+            sync_pool:      SyncPool::new(),
+            async_pool:     AsyncPool::new(),
+            policy:         policy,
+            serial:         FnvHashMap::default(),
+            timed:          BinaryHeap::new(),
+            timed_tasks:    FnvHashMap::default(),
+            capacity:       0,
+            sync_capacity:  0,
+            async_capacity: 0,
+            full_policy:    FullPolicy::Reject,
+            full_stat:      FullStatistics::default(),
+            blocked:        0,
+            aging:          None,
+            aged:           Instant::now(),
+            clock:          None,
+            skew:           Duration::new(0, 0),
+            overdue:        VecDeque::new(),
+            limits:         FnvHashMap::default(),
+            throttled:      (0, 0),
+            classes:        VecDeque::new(),
+            drain:          None,
+            running:        0,
+            workers:        0,
+            observer:       None,
+            type_waits:     vec![Histogram::new(), Histogram::new(), Histogram::new(), Histogram::new()],
+            priority_waits: FnvHashMap::default(),
+            shedding:       None,
+            shed:           0,
+            rng:            PolicyRng::new(),
+            recorder:       None,
+            exclusive:      VecDeque::new(),
+            is_exclusive:   false,
+            released:       Vec::new(),
+            shed_calls:     Vec::new(),
+        }
+    }
+
+    //替换任务池的调度策略，已在任务池中的任务不受影响
+    pub fn set_policy(&mut self, policy: Box<SchedulingPolicy<T>>) {
+        self.policy = policy;
+    }
+
+    //获取任务池的当前时间，定时任务、老化、速率限制、等待时长统计和过载丢弃都使用任务池的时间
+    pub fn now(&self) -> Instant {
+        match self.clock {
+            Some(time) => time,
+            None => Instant::now() + self.skew,
+        }
+    }
+
+    //判断是否使用手动时钟
+    pub fn is_manual_clock(&self) -> bool {
+        self.clock.is_some()
+    }
+
+    //设置是否使用手动时钟，手动时钟从任务池的当前时间开始，只通过推进手动时钟前进，用于确定性的测试，
+    //关闭手动时钟后，任务池时间从手动时钟的时间继续前进，不会倒退
+    pub fn set_manual_clock(&mut self, manual: bool) {
+        if manual {
+            if self.clock.is_none() {
+                self.clock = Some(self.now());
+            }
+        } else if let Some(time) = self.clock.take() {
+            let now = self.now();
+            if time > now {
+                self.skew += time.duration_since(now);
+            }
+        }
+    }
+
+    //推进手动时钟，未使用手动时钟则忽略，推进后已到时间的定时任务需要调用者加入任务队列
+    pub fn advance_clock(&mut self, duration: Duration) {
+        if let Some(ref mut time) = self.clock {
+            *time += duration;
+        }
+    }
+
+    //设置任务池容量，包括等待中的串行任务和定时任务，0表示不限制
+    pub fn set_capacity(&mut self, capacity: u64) {
+        self.capacity = capacity;
+    }
+
+    //设置指定任务类型的容量，包括该类型的延迟任务，0表示不限制
+    pub fn set_type_capacity(&mut self, task_type: TaskType, capacity: u64) {
+        match task_type {
+            TaskType::Async => self.async_capacity = capacity,
+            TaskType::Sync | TaskType::SyncImme => self.sync_capacity = capacity,
+            _ => (),
+        }
+    }
+
+    //获取任务池满时的处理策略
+    pub fn get_full_policy(&self) -> FullPolicy {
+        self.full_policy
+    }
+
+    //设置任务池满时的处理策略
+    pub fn set_full_policy(&mut self, policy: FullPolicy) {
+        self.full_policy = policy;
+    }
+
+    //获取任务池满时的处理统计
+    pub fn full_statistics(&self) -> FullStatistics {
+        self.full_stat.clone()
+    }
+
+    //获取正在等待任务池空间的调用者数量
+    pub fn blocked_size(&self) -> usize {
+        self.blocked
+    }
+
+    //获取任务老化配置
+    pub fn get_aging(&self) -> Option<AgingConfig> {
+        self.aging
+    }
+
+    //设置任务老化配置，None表示不老化，已老化的权重保留到任务被弹出
+    pub fn set_aging(&mut self, aging: Option<AgingConfig>) {
+        self.aging = aging;
+        self.aged = self.now();
+    }
+
+    //设置指定任务类别的速率限制，每秒最多执行rate个任务，最多允许burst个任务连续执行，burst为0则任务永远无法获得令牌，返回错误，任务类别为任务信息，可在运行时调整，
+    //调整后等待令牌的任务可能提前获得令牌，需要唤醒任务池的所有等待者，以便工作者重新计算等待时间
+    pub fn set_rate_limit(&mut self, class: Atom, rate: u64, burst: u64) -> GenResult<(), String> {
+        if burst == 0 {
+            return Err(format!("set rate limit failed, invalid burst, class: {}, rate: {}, burst: {}", *class, rate, burst));
+        }
+
+        let now = self.now();
+        if let Some(bucket) = self.limits.get_mut(&class) {
+            //调整已有的限制，保留当前令牌
+            bucket.refill(now);
+            bucket.limit = Some((rate, burst));
+            bucket.time = now;
+            if bucket.tokens > burst * TOKEN_UNIT {
+                bucket.tokens = burst * TOKEN_UNIT;
+            }
+            return Ok(());
+        }
+        self.limits.insert(class, TokenBucket::new(rate, burst, now));
+        Ok(())
+    }
+
+    //获取指定任务类别的速率限制
+    pub fn get_rate_limit(&self, class: &Atom) -> Option<(u64, u64)> {
+        match self.limits.get(class) {
+            Some(bucket) => bucket.limit,
+            None => None,
+        }
+    }
+
+    //移除指定任务类别的速率限制，因没有令牌而等待的任务会被优先弹出，需要唤醒任务池的所有等待者
+    pub fn remove_rate_limit(&mut self, class: &Atom) {
+        let is_empty = match self.limits.get_mut(class) {
+            Some(bucket) => {
+                bucket.limit = None;
+                bucket.queue.is_empty()
+            },
+            None => return,
+        };
+        if is_empty {
+            self.limits.remove(class);
+        }
+    }
+
+    //获取因没有令牌而等待的任务数量
+    pub fn throttled_size(&self) -> u64 {
+        self.throttled.0 + self.throttled.1
+    }
+
+    //获取最近的定时任务执行时间和因没有令牌而等待的任务获得令牌的时间，没有则返回None，工作者没有可执行的任务时等待到此时间，
+    //已取消的定时任务的时间会延迟移除，所以可能提前返回，独占任务等待或正在执行时暂停分派，不返回获得令牌的时间
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let mut deadline = match self.timed.peek() {
+            Some(&Reverse((time, _))) => Some(time),
+            None => None,
+        };
+        if self.throttled_size() > 0 && !self.is_exclusive && self.exclusive.is_empty() {
+            for bucket in self.limits.values() {
+                if bucket.queue.is_empty() {
+                    continue;
+                }
+
+                match (bucket.ready_time(), deadline) {
+                    (Some(time), Some(min)) if time < min => deadline = Some(time),
+                    (Some(time), None) => deadline = Some(time),
+                    _ => (),
+                }
+            }
+        }
+        deadline
+    }
+
+    //获取任务生命周期观察者
+    pub fn get_observer(&self) -> Option<&PoolObserver> {
+        self.observer.as_ref()
+    }
+
+    //设置任务生命周期观察者，None表示不观察，只影响之后弹出的任务
+    pub fn set_observer(&mut self, observer: Option<PoolObserver>) {
+        self.observer = observer;
+    }
+
+    //获取指定任务类型的等待时长直方图，等待时长为任务进入就绪队列到被弹出的时长
+    pub fn wait_histogram(&self, task_type: TaskType) -> Option<&Histogram> {
+        match type_index(task_type) {
+            Some(index) => self.type_waits.get(index),
+            None => None,
+        }
+    }
+
+    //获取指定优先级的等待时长直方图，优先级为任务被弹出时的优先级
+    pub fn priority_wait_histogram(&self, priority: u64) -> Option<&Histogram> {
+        self.priority_waits.get(&priority)
+    }
+
+    //清空所有等待时长直方图
+    pub fn clear_wait_histograms(&mut self) {
+        for histogram in self.type_waits.iter_mut() {
+            histogram.clear();
+        }
+        self.priority_waits.clear();
+    }
+
+    //设置过载丢弃，每个统计窗口内等待时长的99分位数超过阈值，则下一个统计窗口内弹出的可丢弃任务会被丢弃并调用回调，
+    //回调由调用者在任务池锁外释放take_released的返回值时调用，可以在回调中访问任务池
+    pub fn set_shedding(&mut self, threshold: Duration, window: Duration, callback: ShedCallback) {
+        let now = self.now();
+        self.shedding = Some(ShedState {
+            threshold:  threshold,
+            window:     window,
+            callback:   callback,
+            recent:     Histogram::new(),
+            time:       now,
+            overload:   false,
+        });
+    }
+
+    //移除过载丢弃
+    pub fn remove_shedding(&mut self) {
+        self.shedding = None;
+    }
+
+    //判断任务池是否过载
+    pub fn is_overload(&self) -> bool {
+        match self.shedding {
+            Some(ref state) => state.overload,
+            None => false,
+        }
+    }
+
+    //获取过载时丢弃的任务数量
+    pub fn shed_size(&self) -> u64 {
+        self.shed
+    }
+
+    //取出被丢弃或驱逐的任务的任务函数、被丢弃任务的回调和未写入的调度轨迹，弹出或加入任务后调用，返回值需要在任务池锁外释放，释放时调用丢弃任务的回调并写入调度轨迹
+    pub fn take_released(&mut self) -> Released<T> {
+        Released {
+            payloads:   mem::replace(&mut self.released, Vec::new()),
+            shed:       mem::replace(&mut self.shed_calls, Vec::new()),
+            trace:      match self.recorder {
+                Some(ref mut recorder) => recorder.take_batch(),
+                None => None,
+            },
+        }
+    }
+
+    //设置调度随机数种子，设置后调度策略和延迟任务队列的选择使用确定的伪随机序列
+    pub fn set_seed(&mut self, seed: u64) {
+        let record = self.is_record();
+        self.rng = PolicyRng::with_seed(seed);
+        self.rng.set_record(record);
+    }
+
+    //判断调度是否使用确定的伪随机序列
+    pub fn is_seeded(&self) -> bool {
+        self.rng.is_seeded()
+    }
+
+    //替换调度随机数生成器，返回原随机数生成器，替换后的随机数生成器按是否正在记录调度轨迹记录随机数
+    pub fn replace_rng(&mut self, mut rng: PolicyRng) -> PolicyRng {
+        rng.set_record(self.is_record());
+        mem::replace(&mut self.rng, rng)
+    }
+
+    //指定后续弹出任务时使用的随机数，用于重放调度轨迹
+    pub fn force_draws(&mut self, draws: &[u64]) {
+        self.rng.force(draws);
+    }
+
+    //判断是否正在记录调度轨迹
+    pub fn is_record(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    //设置调度轨迹记录器，记录任务的进入调度、弹出、完成和取消，None表示停止记录，返回原记录器，可在任务池锁外刷新输出
+    pub fn set_recorder(&mut self, recorder: Option<TraceRecorder>) -> Option<TraceRecorder> {
+        self.rng.set_record(recorder.is_some());
+        mem::replace(&mut self.recorder, recorder)
+    }
+
+    //进入排空模式，排空模式下拒绝加入任务并返回Draining类型的加入错误，已加入的任务继续执行直到完成，移除并返回优先级小于指定优先级的任务的任务函数，
+    //优先级为0时保留所有任务，返回的任务函数需要在任务池锁外释放
+    pub fn drain(&mut self, priority: u64) -> Vec<T> {
+        self.drain = Some(priority);
+        if priority == 0 {
+            return Vec::new();
+        }
+
+        let tasks = self.take_tasks(|t| t.get_priority() < priority);
+        let mut funcs = Vec::with_capacity(tasks.len());
+        for mut t in tasks {
+            self.notify_cancel(&t);
+            if let Some(func) = t.take_payload() {
+                funcs.push(func);
+            }
+            self.task_cache.push(t);
+        }
+        funcs
+    }
+
+    //移除所有满足条件的任务，包括同步、异步和延迟任务队列中的任务，以及等待中的串行任务和定时任务，
+    //返回被移除任务的任务信息和任务函数，数量即被移除的任务数量，返回的任务函数需要在任务池锁外释放
+    pub fn remove_by<F: FnMut(&Task<T>) -> bool>(&mut self, f: F) -> Vec<(Atom, T)> {
+        let tasks = self.take_tasks(f);
+        let mut removed = Vec::with_capacity(tasks.len());
+        for mut t in tasks {
+            self.notify_cancel(&t);
+            if let Some(func) = t.take_payload() {
+                removed.push((t.get_info_atom().clone(), func));
+            }
+            self.task_cache.push(t);
+        }
+        removed
+    }
+
+    //移除所有带有指定标签的任务，返回被移除任务的任务信息和任务函数，返回的任务函数需要在任务池锁外释放
+    pub fn remove_by_tag(&mut self, tag: &Atom) -> Vec<(Atom, T)> {
+        self.remove_by(|t| t.has_tag(tag))
+    }
+
+    //退出排空模式，允许加入任务
+    pub fn resume(&mut self) {
+        self.drain = None;
+    }
+
+    //判断是否处于排空模式
+    pub fn is_drain(&self) -> bool {
+        self.drain.is_some()
+    }
+
+    //获取已弹出但未完成的任务数量
+    pub fn running_size(&self) -> u64 {
+        self.running
+    }
+
+    //获取在任务池上工作的工作者数量
+    pub fn worker_size(&self) -> usize {
+        self.workers
+    }
+
+    //工作者开始在任务池上工作，由工作者在当前线程开始工作时调用
+    pub fn attach_worker(&mut self) {
+        self.workers += 1;
+    }
+
+    //工作者停止在任务池上工作，由工作者在当前线程停止工作时调用
+    pub fn detach_worker(&mut self) {
+        if self.workers > 0 {
+            self.workers -= 1;
+        }
+    }
+
+    //判断任务池是否空闲，即没有任何任务，且弹出的任务都已完成
+    pub fn is_idle(&self) -> bool {
+        self.running == 0 && self.total_size() == 0
+    }
+
+    //判断任务池对指定任务类型是否已满
+    pub fn is_full(&self, task_type: TaskType) -> bool {
+        if self.capacity > 0 && self.total_size() >= self.capacity {
+            return true;
+        }
+
+        match task_type {
+            TaskType::Async => {
+                self.async_capacity > 0 && self.async_pool.size() + self.async_pool.delay_size() + self.overdue_size(true) + self.throttled.1 >= self.async_capacity
+            },
+            TaskType::Sync | TaskType::SyncImme => {
+                self.sync_capacity > 0 && self.sync_pool.size() + self.sync_pool.delay_size() + self.overdue_size(false) + self.throttled.0 >= self.sync_capacity
+            },
+            _ => false,
+        }
+    }
+
+    //获取任务数量，包括因没有令牌而等待的任务和等待中的独占任务，不包括等待中的串行任务和未到执行时间的定时任务
+    pub fn size(&self) -> u64 {
+        self.sync_pool.size() + self.sync_pool.delay_size() + self.async_pool.size() + self.async_pool.delay_size() + self.overdue.len() as u64 + self.throttled_size() + self.exclusive.len() as u64
+    }
+
+    //获取等待中的独占任务数量
+    pub fn exclusive_size(&self) -> u64 {
+        self.exclusive.len() as u64
+    }
+
+    //判断是否有独占任务正在执行
+    pub fn is_exclusive(&self) -> bool {
+        self.is_exclusive
+    }
+
+    //获取等待中的串行任务数量
+    pub fn serial_size(&self) -> u64 {
+        let mut size: u64 = 0;
+        for queue in self.serial.values() {
+            size += queue.len() as u64;
+        }
+        size
+    }
+
+    //获取未到执行时间的定时任务数量
+    pub fn timed_size(&self) -> u64 {
+        self.timed_tasks.len() as u64
+    }
+
+    //将已到执行时间的定时任务加入任务队列，返回加入的任务数量
+    pub fn poll_timed(&mut self) -> usize {
+        if self.timed.is_empty() {
+            return 0;
+        }
+
+        let now = self.now();
+        let mut count = 0;
+        loop {
+            let uid = match self.timed.peek() {
+                Some(&Reverse((time, uid))) if time <= now => uid,
+                _ => break,
+            };
+            self.timed.pop();
+            if let Some((task_type, task)) = self.timed_tasks.remove(&uid) {
+                self.trace(|| TraceEvent::push(&task));
+                self.enqueue(task_type, task);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    //从任务池中弹出一个任务，优先弹出已获得令牌的等待任务和超过最大等待时间的任务，其次由调度策略从同步和异步任务队列中选择，都为空时才从延迟任务队列中弹出，
+    //弹出的任务没有令牌则继续等待，并弹出其它任务，有独占任务等待时暂停分派，直到已弹出的任务都完成后弹出独占任务，独占任务完成前不弹出任何任务，
+    //没有可执行的任务返回false，弹出的任务在完成前计入正在执行的任务，必须调用complete完成，否则等待的独占任务永远不会弹出，任务池也不会空闲，
+    //不会完成任务的调用者应使用pop_untracked
+    pub fn pop(&mut self, task: &mut Task<T>) -> bool {
+        if self.is_exclusive {
+            return false; //独占任务正在执行
+        }
+
+        self.age();
+        let now = self.now();
+        loop {
+            let (mut t, queue) = match self.take_next(now) {
+                Some(r) => r,
+                None => {
+                    //没有弹出任务，则丢弃选择队列时生成的随机数，避免记录到下一次弹出
+                    self.rng.clear_draws();
+                    return false;
+                },
+            };
+
+            let wait = match t.get_time() {
+                Some(time) if now > time => now.duration_since(time),
+                _ => Duration::new(0, 0),
+            };
+            self.record_wait(&t, wait, now);
+            if self.recorder.is_some() {
+                let draws = self.rng.take_draws();
+                self.trace(|| TraceEvent::Pop {
+                    uid:    t.get_uid(),
+                    queue:  queue,
+                    draws:  draws,
+                    worker: current_worker(),
+                });
+            }
+            if t.is_sheddable() && self.is_overload() {
+                //任务池过载，则丢弃可丢弃的任务
+                self.shed_task(t, wait);
+                continue;
+            }
+
+            //填充任务
+            if let TaskType::Exclusive = t.get_type() {
+                self.is_exclusive = true;
+            }
+            self.running += 1;
+            t.move_to(task);
+            task.set_observer(self.observer.clone());
+            self.observe(&t, |o, e| o.on_pop(e));
+            self.free(Some(t));
+            return true;
+        }
+    }
+
+    //从任务池中弹出一个任务，并立即完成，不计入正在执行的任务，用于把任务池作为消息队列使用的调用者，
+    //弹出的串行任务不再阻塞同一个键的下一个任务，弹出的独占任务也不再阻塞其它任务，没有可执行的任务返回false
+    pub fn pop_untracked(&mut self, task: &mut Task<T>) -> bool {
+        if !self.pop(task) {
+            return false;
+        }
+        self.complete(task);
+        true
+    }
+
+    //向任务池加入一个带有指定标签的任务，返回任务唯一id，可按标签批量移除
+    pub fn push_tagged(&mut self, task_type: TaskType, priority: u64, payload: T, info: Atom, tags: Vec<Atom>) -> GenResult<usize, PushError<T>> {
+        self.push_with(task_type, priority, payload, info, tags, false)
+    }
+
+    //向任务池加入一个可丢弃的任务，任务池过载时会被丢弃，返回任务唯一id
+    pub fn push_sheddable(&mut self, task_type: TaskType, priority: u64, payload: T, info: Atom) -> GenResult<usize, PushError<T>> {
+        self.push_with(task_type, priority, payload, info, Vec::new(), true)
+    }
+
+    //向任务池加入一个带有指定标签和是否可丢弃的任务，返回任务唯一id
+    pub fn push_with(&mut self, task_type: TaskType, priority: u64, payload: T, info: Atom, tags: Vec<Atom>, sheddable: bool) -> GenResult<usize, PushError<T>> {
+        let payload = self.reserve(task_type, priority, payload)?;
+        let mut task = self.new_task(task_type, priority, payload, info);
+        let uid = task.get_uid();
+        task.set_tags(tags);
+        task.set_sheddable(sheddable);
+        self.notify_push(&task);
+        self.enqueue(task_type, task);
+        Ok(uid)
+    }
+
+    //从任务池中取出下一个可执行的任务和任务所在的队列，没有令牌的任务继续等待
+    fn take_next(&mut self, now: Instant) -> Option<(Task<T>, TraceQueue)> {
+        if !self.exclusive.is_empty() {
+            if self.running > 0 {
+                return None; //等待已弹出的任务完成
+            }
+            return self.exclusive.pop_front().map(|t| (t, TraceQueue::Exclusive));
+        }
+
+        let mut wait_free = self.pop_throttled(now).map(|t| (t, TraceQueue::Throttled));
+        while wait_free.is_none() {
+            let next = match self.overdue.pop_front() {
+                Some((_, t)) => Some((t, TraceQueue::Overdue)),
+                None => {
+                    match self.policy.pop(&mut self.sync_pool, &mut self.async_pool, &mut self.rng) {
+                        None => self.delay_pop().map(|t| {
+                            let queue = TraceQueue::of(t.get_type(), true);
+                            (t, queue)
+                        }),
+                        Some(t) => {
+                            let queue = TraceQueue::of(t.get_type(), false);
+                            Some((t, queue))
+                        },
+                    }
+                },
+            };
+            match next {
+                None => break,
+                Some((t, queue)) => {
+                    if self.acquire_token(&t, now) {
+                        wait_free = Some((t, queue));
+                    } else {
+                        self.throttle(t);
+                    }
+                },
+            }
+        }
+        wait_free
+    }
+
+    //记录指定任务的等待时长，统计窗口结束时判断任务池是否过载
+    fn record_wait(&mut self, task: &Task<T>, wait: Duration, now: Instant) {
+        if let Some(index) = type_index(task.get_type()) {
+            self.type_waits[index].record(wait);
+        }
+        self.priority_waits.entry(task.get_priority()).or_insert_with(Histogram::new).record(wait);
+
+        if let Some(ref mut state) = self.shedding {
+            if now > state.time && now.duration_since(state.time) >= state.window {
+                state.overload = state.recent.count() > 0 && state.recent.percentile(0.99) > state.threshold;
+                state.recent.clear();
+                state.time = now;
+            }
+            state.recent.record(wait);
+        }
+    }
+
+    //丢弃指定任务，并记录丢弃任务的回调，由调用者在任务池锁外调用
+    fn shed_task(&mut self, mut task: Task<T>, wait: Duration) {
+        self.shed += 1;
+        self.notify_cancel(&task);
+        if let Some(key) = task.get_serial().cloned() {
+            self.next_serial(&key);
+        }
+        if let Some(func) = task.take_payload() {
+            self.released.push(func); //被丢弃任务的任务函数由调用者在任务池锁外释放
+        }
+        if let Some(ref state) = self.shedding {
+            //丢弃任务的回调由调用者在任务池锁外调用
+            self.shed_calls.push((state.callback.clone(), task.get_uid(), task.get_info_atom().clone(), wait));
+        }
+        self.task_cache.push(task);
+    }
+
+    //向任务池加入一个任务，返回任务唯一id，可用于在任务被弹出前取消任务，任务池已满则按处理策略驱逐任务或返回错误
+    pub fn push(&mut self, task_type: TaskType, priority: u64, payload: T, info: Atom) -> GenResult<usize, PushError<T>> {
+        let payload = self.reserve(task_type, priority, payload)?;
+        let task = self.new_task(task_type, priority, payload, info);
+        let uid = task.get_uid();
+        self.notify_push(&task);
+        self.enqueue(task_type, task);
+        Ok(uid)
+    }
+
+    //向任务池加入一个指定键的串行任务，返回任务唯一id，同一个键的任务按加入顺序执行，且同时只会有一个在执行
+    pub fn push_serial(&mut self, key: Atom, task_type: TaskType, priority: u64, payload: T, info: Atom) -> GenResult<usize, PushError<T>> {
+        let payload = self.reserve(task_type, priority, payload)?;
+        let mut task = self.new_task(task_type, priority, payload, info);
+        let uid = task.get_uid();
+        task.set_serial(Some(key.clone()));
+        self.notify_push(&task);
+        if let Some(queue) = self.serial.get_mut(&key) {
+            //该键已有任务在队列中或正在执行，则等待
+            queue.push_back((task_type, task));
+            return Ok(uid);
+        }
+        self.serial.insert(key, VecDeque::new());
+        self.enqueue(task_type, task);
+        Ok(uid)
+    }
+
+    //向任务池加入一个在指定时间执行的定时任务，返回任务唯一id，到达执行时间后才会加入任务队列
+    pub fn push_at(&mut self, task_type: TaskType, priority: u64, payload: T, info: Atom, time: Instant) -> GenResult<usize, PushError<T>> {
+        let payload = self.reserve(task_type, priority, payload)?;
+        let task = self.new_task(task_type, priority, payload, info);
+        let uid = task.get_uid();
+        self.observe(&task, |o, e| o.on_push(e)); //定时任务在到达执行时间后才记录调度轨迹
+        self.timed.push(Reverse((time, uid)));
+        self.timed_tasks.insert(uid, (task_type, task));
+        Ok(uid)
+    }
+
+    //向任务池加入一个在指定时长后执行的定时任务，返回任务唯一id
+    pub fn push_after(&mut self, task_type: TaskType, priority: u64, payload: T, info: Atom, delay: Duration) -> GenResult<usize, PushError<T>> {
+        let time = self.now() + delay;
+        self.push_at(task_type, priority, payload, info, time)
+    }
+
+    //完成指定的已弹出任务，如果是串行任务，则将同一个键的下一个等待任务加入任务队列，返回是否有任务加入，
+    //独占任务完成后恢复分派，已弹出的任务都完成且有独占任务等待时，也返回true，每个由pop弹出的任务只能完成一次
+    pub fn complete(&mut self, task: &mut Task<T>) -> bool {
+        debug_assert!(self.running > 0, "complete task without a matching pop, uid: {}", task.get_uid());
+        if self.running > 0 {
+            self.running -= 1;
+        }
+        self.trace(|| TraceEvent::Complete { uid: task.get_uid() });
+        let resume = match task.get_type() {
+            TaskType::Exclusive if self.is_exclusive => {
+                self.is_exclusive = false;
+                self.size() > 0
+            },
+            _ => self.running == 0 && !self.exclusive.is_empty(),
+        };
+        let key = task.get_serial().cloned();
+        task.set_serial(None); //保证只完成一次
+        match key {
+            Some(ref key) => self.next_serial(key) || resume,
+            None => resume,
+        }
+    }
+
+    //取消指定唯一id的任务，成功返回未执行的任务函数，任务已被弹出或不存在则返回None
+    pub fn cancel(&mut self, uid: usize) -> Option<T> {
+        if uid == 0 {
+            return None;
+        }
+
+        let task = match self.sync_pool.cancel(uid) {
+            None => self.async_pool.cancel(uid),
+            r => r,
+        };
+        let task = match task {
+            None => {
+                match self.overdue.iter().position(|&(_, ref t)| t.get_uid() == uid) {
+                    Some(index) => self.overdue.remove(index).map(|(_, t)| t),
+                    None => {
+                        match self.exclusive.iter().position(|t| t.get_uid() == uid) {
+                            Some(index) => self.exclusive.remove(index),
+                            None => self.cancel_throttled(uid),
+                        }
+                    },
+                }
+            },
+            r => r,
+        };
+        let task = match task {
+            Some(t) => {
+                if let Some(key) = t.get_serial() {
+                    //取消的是该键在任务队列中的串行任务，则继续下一个等待任务
+                    self.next_serial(key);
+                }
+                Some(t)
+            },
+            None => {
+                match self.timed_tasks.remove(&uid) {
+                    Some((_, t)) => {
+                        self.shrink_timed();
+                        Some(t)
+                    },
+                    None => self.cancel_serial(uid),
+                }
+            },
+        };
+        match task {
+            Some(mut t) => {
+                self.notify_cancel(&t);
+                let func = t.take_payload();
+                self.task_cache.push(t);
+                func
+            },
+            None => None,
+        }
+    }
+
+    //移除指定优先级的同步任务，返回被移除任务的任务函数，返回的任务函数需要在任务池锁外释放
+    pub fn remove_sync_task(&mut self, priority: u64) -> Vec<T> {
+        let mut tasks = self.sync_pool.remove(priority);
+        let overdue: Vec<(TaskType, Task<T>)> = self.overdue.drain(..).collect();
+        for (task_type, t) in overdue {
+            match task_type {
+                TaskType::Async => self.overdue.push_back((task_type, t)),
+                _ if t.get_aged_priority() != priority => self.overdue.push_back((task_type, t)),
+                _ => tasks.push(t),
+            }
+        }
+        for bucket in self.limits.values_mut() {
+            let is_remove = |t: &Task<T>| {
+                match t.get_type() {
+                    TaskType::Async => false,
+                    _ => t.get_aged_priority() == priority,
+                }
+            };
+            let removed = take_by(&mut bucket.queue, is_remove);
+            self.throttled.0 -= removed.len() as u64;
+            tasks.extend(removed);
+        }
+        let mut funcs = Vec::with_capacity(tasks.len());
+        for mut t in tasks {
+            self.notify_cancel(&t);
+            if let Some(key) = t.get_serial() {
+                self.next_serial(key);
+            }
+            if let Some(func) = t.take_payload() {
+                funcs.push(func);
+            }
+            self.task_cache.push(t);
+        }
+        funcs
+    }
+
+    //清空所有未弹出的任务，返回这些任务的任务函数，返回的任务函数需要在任务池锁外释放，
+    //正在执行的串行任务的键保留到任务完成，之后加入的同一个键的任务仍然等待正在执行的任务完成
+    pub fn clear(&mut self) -> Vec<T> {
+        let tasks = self.take_tasks(|_| true);
+        let mut funcs = Vec::with_capacity(tasks.len());
+        for mut t in tasks {
+            self.notify_cancel(&t);
+            if let Some(func) = t.take_payload() {
+                funcs.push(func);
+            }
+            self.task_cache.push(t);
+        }
+        self.async_pool.clear();
+        self.sync_pool.clear();
+        self.timed.clear();
+        self.timed_tasks.clear();
+        self.overdue.clear();
+        self.exclusive.clear();
+        for bucket in self.limits.values_mut() {
+            bucket.queue.clear();
+        }
+        self.limits.retain(|_, bucket| bucket.limit.is_some());
+        self.throttled = (0, 0);
+        self.classes.clear();
+        funcs
+    }
+
+    //取出所有满足条件的任务，串行任务被取出后继续同一个键的下一个等待任务
+    fn take_tasks<F: FnMut(&Task<T>) -> bool>(&mut self, mut f: F) -> Vec<Task<T>> {
+        let mut tasks = Vec::new();
+        for queue in self.serial.values_mut() {
+            if !queue.iter().any(|&(_, ref t)| f(t)) {
+                continue;
+            }
+
+            let waits: Vec<(TaskType, Task<T>)> = queue.drain(..).collect();
+            for (task_type, t) in waits {
+                if f(&t) {
+                    tasks.push(t);
+                } else {
+                    queue.push_back((task_type, t));
+                }
+            }
+        }
+        let uids: Vec<usize> = self.timed_tasks.values().filter(|&&(_, ref t)| f(t)).map(|&(_, ref t)| t.get_uid()).collect();
+        for uid in uids {
+            if let Some((_, t)) = self.timed_tasks.remove(&uid) {
+                tasks.push(t);
+            }
+        }
+        self.shrink_timed();
+
+        //移除任务队列中的任务，会同时更新任务池的权重
+        let mut removed = self.sync_pool.take_by(&mut f);
+        removed.extend(self.sync_pool.delay_take_by(&mut f));
+        removed.extend(self.async_pool.take_by(&mut f));
+        removed.extend(self.async_pool.delay_take_by(&mut f));
+        let overdue: Vec<(TaskType, Task<T>)> = self.overdue.drain(..).collect();
+        for (task_type, t) in overdue {
+            if f(&t) {
+                removed.push(t);
+            } else {
+                self.overdue.push_back((task_type, t));
+            }
+        }
+        removed.extend(take_by(&mut self.exclusive, &mut f));
+        for bucket in self.limits.values_mut() {
+            for t in take_by(&mut bucket.queue, &mut f) {
+                match t.get_type() {
+                    TaskType::Async => self.throttled.1 -= 1,
+                    _ => self.throttled.0 -= 1,
+                }
+                removed.push(t);
+            }
+        }
+        for t in removed {
+            if let Some(key) = t.get_serial() {
+                self.next_serial(key);
+            }
+            tasks.push(t);
+        }
+        tasks
+    }
+
+    //为指定类型的任务预留空间，任务池已满时按处理策略驱逐延迟任务，无法预留则返回错误，错误中交还任务负载，避免在任务池锁内释放
+    fn reserve(&mut self, task_type: TaskType, priority: u64, payload: T) -> GenResult<T, PushError<T>> {
+        if self.drain.is_some() {
+            self.full_stat.rejected += 1;
+            return Err(PushError::new(PushErrorKind::Draining, format!("task pool draining, task_type: {:?}, priority: {}", task_type, priority), payload));
+        }
+
+        if !self.is_full(task_type) {
+            return Ok(payload);
+        }
+
+        if let FullPolicy::EvictDelay = self.full_policy {
+            //加入的不是延迟任务，才驱逐延迟任务
+            if priority > 0 {
+                let evicted = match task_type {
+                    TaskType::Async => self.async_pool.delay_pop_back(),
+                    _ => self.sync_pool.delay_pop_back(),
+                };
+                let evicted = match evicted {
+                    None if self.capacity > 0 && self.total_size() >= self.capacity => {
+                        //任务池已满，则可以驱逐其它类型的延迟任务
+                        match task_type {
+                            TaskType::Async => self.sync_pool.delay_pop_back(),
+                            _ => self.async_pool.delay_pop_back(),
+                        }
+                    },
+                    r => r,
+                };
+                if let Some(mut t) = evicted {
+                    self.full_stat.evicted += 1;
+                    self.notify_cancel(&t);
+                    if let Some(func) = t.take_payload() {
+                        self.released.push(func); //被驱逐任务的任务函数由调用者在任务池锁外释放
+                    }
+                    if let Some(key) = t.get_serial() {
+                        self.next_serial(key);
+                    }
+                    self.task_cache.push(t);
+                    if !self.is_full(task_type) {
+                        return Ok(payload);
+                    }
+                }
+            }
+        }
+
+        self.full_stat.rejected += 1;
+        Err(PushError::new(PushErrorKind::Full, format!("task pool full, task_type: {:?}, priority: {}", task_type, priority), payload))
+    }
+
+    //获取任务池中所有任务的数量
+    fn total_size(&self) -> u64 {
+        self.size() + self.serial_size() + self.timed_size()
+    }
+
+    //构建一个新任务
+    fn new_task(&mut self, task_type: TaskType, priority: u64, payload: T, info: Atom) -> Task<T> {
+        self.counter += 1; //任务唯一id从1开始，0表示无效任务
+        let mut task: Task<T> = self.task_cache.pop();
+        task.set_uid(self.counter);
+        task.set_priority(priority);
+        task.set_type(task_type);
+        task.set_payload(Some(payload));
+        task.set_info(info);
+        task.set_context(context::current()); //捕获加入任务的线程的上下文
+        task
+    }
+
+    //获取超过最大等待时间的异步或同步任务数量
+    fn overdue_size(&self, is_async: bool) -> u64 {
+        self.overdue.iter().filter(|&&(task_type, _)| {
+            match task_type {
+                TaskType::Async => is_async,
+                _ => !is_async,
+            }
+        }).count() as u64
+    }
+
+    //将任务加入任务队列
+    fn enqueue(&mut self, task_type: TaskType, mut task: Task<T>) {
+        if let TaskType::Exclusive = task_type {
+            //独占任务不受调度策略影响，按加入顺序执行
+            task.set_time(Some(self.now()));
+            self.exclusive.push_back(task);
+            return;
+        }
+
+        self.policy.on_push(&mut task);
+        task.set_time(Some(self.now()));
+        if task.get_priority() > 0 {
+            match task_type {
+                TaskType::Async => {
+                    //加入异步任务队列
+                    self.async_pool.push_back(task);
+                },
+                TaskType::Sync => {
+                    //加入同步任务队列尾
+                    self.sync_pool.push_back(task);
+                },
+                TaskType::SyncImme => {
+                    //加入同步任务队列头
+                    self.sync_pool.push_front(task);
+                },
+                _ => (),
+            }
+        } else {
+            //加入延迟任务队列
+            match task_type {
+                TaskType::Async => {
+                    //加入异步延迟任务队列
+                    self.async_pool.delay_push_back(task);
+                },
+                TaskType::Sync => {
+                    //加入同步延迟任务队列尾
+                    self.sync_pool.delay_push_back(task);
+                },
+                TaskType::SyncImme => {
+                    //加入同步延迟任务队列头
+                    self.sync_pool.delay_push_front(task);
+                },
+                _ => (),
+            }
+        }
+    }
+
+    //将指定键的下一个等待任务加入任务队列，没有等待任务则释放该键，返回是否有任务加入
+    fn next_serial(&mut self, key: &Atom) -> bool {
+        let next = match self.serial.get_mut(key) {
+            Some(queue) => queue.pop_front(),
+            None => return false,
+        };
+        match next {
+            Some((task_type, task)) => {
+                self.enqueue(task_type, task);
+                true
+            },
+            None => {
+                self.serial.remove(key);
+                false
+            },
+        }
+    }
+
+    //定时任务的执行时间堆中失效的时间过多时，移除失效的时间并重建
+    fn shrink_timed(&mut self) {
+        if self.timed.len() <= TIMED_SHRINK_SIZE || self.timed.len() <= self.timed_tasks.len() * 2 {
+            return;
+        }
+
+        let timed = mem::replace(&mut self.timed, BinaryHeap::new()).into_vec();
+        let timed: Vec<Reverse<(Instant, usize)>> = timed.into_iter().filter(|&Reverse((_, uid))| self.timed_tasks.contains_key(&uid)).collect();
+        self.timed = BinaryHeap::from(timed);
+    }
+
+    //从等待中的串行任务中移除指定唯一id的任务
+    fn cancel_serial(&mut self, uid: usize) -> Option<Task<T>> {
+        for queue in self.serial.values_mut() {
+            let pos = queue.iter().position(|&(_, ref t)| t.get_uid() == uid);
+            if let Some(index) = pos {
+                return queue.remove(index).map(|(_, t)| t);
+            }
+        }
+        None
+    }
+
+    //到达老化周期时，老化所有等待中的任务，并将超过最大等待时间的任务移入超时队列
+    fn age(&mut self) {
+        let config = match self.aging {
+            Some(config) => config,
+            None => return,
+        };
+        let now = self.now();
+        let interval = millis(config.interval).max(1);
+        let periods = millis(now.duration_since(self.aged)) / interval;
+        if periods == 0 {
+            return;
+        }
+        self.aged += Duration::from_millis(periods * interval);
+
+        //老化同步和异步任务队列
+        let increase = config.rate * periods;
+        if increase > 0 {
+            self.sync_pool.age(increase, config.cap);
+            self.async_pool.age(increase, config.cap);
+        }
+
+        //延迟任务队列中老化后优先级不小于1的任务，以老化增加的优先级加入同步或异步任务队列，不修改任务的原始优先级
+        if config.rate > 0 {
+            let is_aged = |t: &Task<T>| aged_priority(t, &config, now) > 0;
+            for mut t in self.sync_pool.delay_take_by(&is_aged) {
+                let boost = aged_priority(&t, &config, now) - t.get_priority();
+                t.set_boost(boost);
+                self.sync_pool.push_back(t);
+            }
+            for mut t in self.async_pool.delay_take_by(&is_aged) {
+                let boost = aged_priority(&t, &config, now) - t.get_priority();
+                t.set_boost(boost);
+                self.async_pool.push_back(t);
+            }
+        }
+
+        //检查所有任务队列中的任务，队列头加入的任务可能比队列中的任务更新，移出超过最大等待时间的任务，按等待时间从长到短加入超时队列
+        if let Some(max_wait) = config.max_wait {
+            let is_overdue = |t: &Task<T>| {
+                match t.get_time() {
+                    Some(time) => now.duration_since(time) >= max_wait,
+                    None => false,
+                }
+            };
+            let mut overdue = Vec::new();
+            for t in self.sync_pool.take_by(&is_overdue).into_iter().chain(self.sync_pool.delay_take_by(&is_overdue)) {
+                overdue.push((TaskType::Sync, t));
+            }
+            for t in self.async_pool.take_by(&is_overdue).into_iter().chain(self.async_pool.delay_take_by(&is_overdue)) {
+                overdue.push((TaskType::Async, t));
+            }
+            overdue.sort_by_key(|&(_, ref t)| t.get_time());
+            self.overdue.extend(overdue);
+        }
+    }
+
+    //为指定任务获取令牌，任务类别没有速率限制或获取到令牌返回true
+    fn acquire_token(&mut self, task: &Task<T>, now: Instant) -> bool {
+        if self.limits.is_empty() {
+            return true;
+        }
+
+        match self.limits.get_mut(task.get_info_atom()) {
+            Some(bucket) => bucket.queue.is_empty() && bucket.acquire(now),
+            None => true,
+        }
+    }
+
+    //任务因没有令牌而等待
+    fn throttle(&mut self, task: Task<T>) {
+        match task.get_type() {
+            TaskType::Async => self.throttled.1 += 1,
+            _ => self.throttled.0 += 1,
+        }
+        if let Some(bucket) = self.limits.get_mut(task.get_info_atom()) {
+            if bucket.queue.is_empty() && !self.classes.contains(task.get_info_atom()) {
+                //任务类别开始有等待任务，则加入轮询顺序的末尾
+                self.classes.push_back(task.get_info_atom().clone());
+            }
+            bucket.queue.push_back(task);
+        }
+    }
+
+    //弹出已获得令牌的等待任务
+    fn pop_throttled(&mut self, now: Instant) -> Option<Task<T>> {
+        if self.throttled_size() == 0 {
+            return None;
+        }
+
+        //按轮询顺序检查有等待任务的任务类别，获得令牌的任务类别移到末尾，避免先加入的任务类别总是优先获得令牌
+        let mut reply = None;
+        let mut empty = None;
+        for _ in 0..self.classes.len() {
+            let class = match self.classes.pop_front() {
+                Some(class) => class,
+                None => break,
+            };
+            let bucket = match self.limits.get_mut(&class) {
+                Some(bucket) if !bucket.queue.is_empty() => bucket,
+                _ => continue, //等待任务已被取消或移除，则移除任务类别
+            };
+            if !bucket.acquire(now) {
+                self.classes.push_back(class);
+                continue;
+            }
+
+            reply = bucket.queue.pop_front();
+            if !bucket.queue.is_empty() {
+                self.classes.push_back(class);
+            } else if bucket.limit.is_none() {
+                //已移除限制的任务类别没有等待任务，则移除令牌桶
+                empty = Some(class);
+            }
+            break;
+        }
+        if let Some(class) = empty {
+            self.limits.remove(&class);
+        }
+        if let Some(ref t) = reply {
+            match t.get_type() {
+                TaskType::Async => self.throttled.1 -= 1,
+                _ => self.throttled.0 -= 1,
+            }
+        }
+        reply
+    }
+
+    //从因没有令牌而等待的任务中移除指定唯一id的任务
+    fn cancel_throttled(&mut self, uid: usize) -> Option<Task<T>> {
+        let mut reply = None;
+        for bucket in self.limits.values_mut() {
+            let pos = bucket.queue.iter().position(|t| t.get_uid() == uid);
+            if let Some(index) = pos {
+                reply = bucket.queue.remove(index);
+                break;
+            }
+        }
+        if let Some(ref t) = reply {
+            match t.get_type() {
+                TaskType::Async => self.throttled.1 -= 1,
+                _ => self.throttled.0 -= 1,
+            }
+        }
+        reply
+    }
+
+    //从延迟任务队列中弹出任务
+    fn delay_pop(&mut self) -> Option<Task<T>> {
+        let sw = self.sync_pool.delay_size();
+        let aw = self.async_pool.delay_size();
+        if sw > 0 {
+            if aw > 0 {
+                //判断从同步还是异步延迟任务队列中弹出
+                if self.rng.gen_range(0, sw + aw) < sw {
+                    //从同步延迟任务队列中弹出
+                    self.sync_pool.delay_pop()
+                } else {
+                    //从异步延迟任务队列中弹出
+                    self.async_pool.delay_pop()
+                }
+            } else {
+                //只有从同步延迟任务队列中弹出
+                self.sync_pool.delay_pop()
+            }
+        } else if aw > 0 {
+            //只有从异步延迟任务队列中弹出
+            self.async_pool.delay_pop()
+        } else {
+            None
+        }
+    }
+
+    //通知观察者指定任务的事件
+    fn observe<F: FnOnce(&TaskObserver, &TaskEvent)>(&self, task: &Task<T>, f: F) {
+        if let Some(ref observer) = self.observer {
+            f(&**observer.get_observer(), &observer.event(task, None));
+        }
+    }
+
+    //记录调度事件
+    fn trace<F: FnOnce() -> TraceEvent>(&mut self, f: F) {
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record(&f());
+        }
+    }
+
+    //指定任务加入任务池，通知观察者并记录调度轨迹
+    fn notify_push(&mut self, task: &Task<T>) {
+        self.observe(task, |o, e| o.on_push(e));
+        self.trace(|| TraceEvent::push(task));
+    }
+
+    //指定任务被取消、驱逐、移除或丢弃，通知观察者并记录调度轨迹
+    fn notify_cancel(&mut self, task: &Task<T>) {
+        self.observe(task, |o, e| o.on_cancel(e));
+        self.trace(|| TraceEvent::Cancel { uid: task.get_uid() });
+    }
+
+    //释放指定任务
+    fn free(&mut self, task: Option<Task<T>>) {
+        match task {
+            Some(t) => self.task_cache.push(t),
+            None => (),
+        }
+    }
+}
+
+/*
+* 线程安全的向指定任务池加入任务，任务池已满且处理策略为阻塞时，阻塞调用者直到任务池有空间或超时，加入失败时在错误中交还任务负载
+*/
+pub fn push_task<T, F>(sync: &Arc<(Mutex<TaskPool<T>>, Condvar)>, task_type: TaskType, push: F) -> GenResult<usize, PushError<T>>
+    where F: FnOnce(&mut TaskPool<T>) -> GenResult<usize, PushError<T>> {
+    let &(ref lock, ref cvar) = &**sync;
+    let mut task_pool = lock.lock().unwrap();
+    let mut is_timeout = false;
+    if let FullPolicy::Block(timeout) = (*task_pool).get_full_policy() {
+        if (*task_pool).is_full(task_type) {
+            (*task_pool).full_stat.blocked += 1;
+            let time = Instant::now();
+            while (*task_pool).is_full(task_type) {
+                let elapsed = time.elapsed();
+                if elapsed >= timeout {
+                    //等待超时，仍然调用加入函数，由任务池拒绝加入并交还任务负载
+                    (*task_pool).full_stat.timeout += 1;
+                    is_timeout = true;
+                    break;
+                }
+
+                (*task_pool).blocked += 1;
+                let (pool, _) = cvar.wait_timeout(task_pool, timeout - elapsed).unwrap();
+                task_pool = pool;
+                (*task_pool).blocked -= 1;
+            }
+        }
+    }
+
+    let result = push(&mut *task_pool);
+    let released = (*task_pool).take_released();
+    drop(task_pool);
+    drop(released); //在任务池锁外释放被驱逐的任务的任务函数
+    match result {
+        Err(e) => {
+            if is_timeout {
+                return Err(PushError::new(PushErrorKind::Timeout, format!("task pool full, wait timeout, task_type: {:?}", task_type), e.into_payload()));
+            }
+            Err(e)
+        },
+        Ok(uid) => {
+            cvar.notify_one();
+            Ok(uid)
+        },
+    }
+}
+
+/*
+* 线程安全的等待指定任务池空闲，即没有任何任务，且弹出的任务都已完成，通常在排空模式下使用，返回是否空闲，超时返回false
+*/
+pub fn wait_idle<T>(sync: &Arc<(Mutex<TaskPool<T>>, Condvar)>, timeout: Duration) -> bool {
+    let &(ref lock, ref cvar) = &**sync;
+    let time = Instant::now();
+    let mut task_pool = lock.lock().unwrap();
+    while !(*task_pool).is_idle() {
+        let elapsed = time.elapsed();
+        if elapsed >= timeout {
+            return false;
+        }
+
+        //工作者完成任务后任务池空闲会唤醒所有等待者，同时限制单次等待时长，避免错过唤醒
+        let wait = timeout - elapsed;
+        let wait = if wait > Duration::from_millis(IDLE_WAIT_TIMEOUT) { Duration::from_millis(IDLE_WAIT_TIMEOUT) } else { wait };
+        let (pool, _) = cvar.wait_timeout(task_pool, wait).unwrap();
+        task_pool = pool;
+    }
+    true
+}
+
+//获取指定任务类型的等待时长直方图的位置，空任务返回None
+fn type_index(task_type: TaskType) -> Option<usize> {
+    match task_type {
+        TaskType::Async => Some(0),
+        TaskType::Sync => Some(1),
+        TaskType::SyncImme => Some(2),
+        TaskType::Exclusive => Some(3),
+        TaskType::Empty => None,
+    }
+}
+
+//移除任务队列中所有满足条件的任务，返回被移除的任务
+fn take_by<T, F: FnMut(&Task<T>) -> bool>(queue: &mut VecDeque<Task<T>>, mut f: F) -> Vec<Task<T>> {
+    let mut tasks = Vec::new();
+    if !queue.iter().any(|t| f(t)) {
+        return tasks;
+    }
+
+    let all: Vec<Task<T>> = queue.drain(..).collect();
+    for t in all {
+        if f(&t) {
+            tasks.push(t);
+        } else {
+            queue.push_back(t);
+        }
+    }
+    tasks
+}
+
+//获取任务老化后的优先级，即任务优先级加上等待的老化周期数乘以老化速率，不超过上限
+fn aged_priority<T>(task: &Task<T>, config: &AgingConfig, now: Instant) -> u64 {
+    let priority = task.get_priority();
+    if priority >= config.cap {
+        return priority;
+    }
+
+    let wait = match task.get_time() {
+        Some(time) => millis(now.duration_since(time)),
+        None => return priority,
+    };
+    let aged = priority + config.rate * (wait / millis(config.interval).max(1));
+    if aged > config.cap {
+        config.cap
+    } else {
+        aged
+    }
+}
+
+//获取时长的毫秒数
+fn millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + (duration.subsec_nanos() / 1000000) as u64
 }
\ No newline at end of file
@@ -9,6 +9,8 @@ use std::mem::transmute;
 use pi_lib::wheel::{Wheel, Item};
 use pi_lib::time::{now_millis};
 
+use context::{current, run_with};
+
 lazy_static! {
 	pub static ref TIMER: Timer = Timer::new(10);
 }
@@ -64,6 +66,8 @@ impl Timer{
 
 	pub fn set_timeout(&self, f: Box<FnBox()>, ms: u32) -> Arc<AtomicUsize>{
 		self.statistics.all_count.fetch_add(1, Ordering::Relaxed);
+        let context = current(); //回调在设置定时器的线程的上下文中执行
+        let f: Box<FnBox()> = Box::new(move || run_with(context, move || f()));
         let mut w = TIMER.wheel.lock().unwrap();
         let time = w.time;
 		w.insert(Item{elem: unsafe { transmute(f) }, time_point: time + (ms as u64)})
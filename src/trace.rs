@@ -0,0 +1,584 @@
+use std::mem;
+use std::fs::File;
+use std::path::Path;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, Condvar};
+use std::io::{Read, Write, BufReader, BufWriter};
+use std::result::Result as GenResult;
+use std::fmt::{Display, Formatter, Result};
+
+use fnv::FnvHashMap;
+
+use pi_lib::atom::Atom;
+use task::{TaskType, Task};
+use task_pool::TaskPool;
+
+/*
+* 调度轨迹的文件头和版本
+*/
+const TRACE_MAGIC: &'static [u8] = b"PITR";
+const TRACE_VERSION: u8 = 2;
+
+/*
+* 调度事件的标记
+*/
+const TRACE_PUSH: u8 = 1;
+const TRACE_POP: u8 = 2;
+const TRACE_COMPLETE: u8 = 3;
+const TRACE_CANCEL: u8 = 4;
+
+/*
+* 弹出任务时选择的队列
+*/
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceQueue {
+    Sync = 0,   //同步任务队列
+    Async,      //异步任务队列
+    SyncDelay,  //同步延迟任务队列
+    AsyncDelay, //异步延迟任务队列
+    Overdue,    //超过最大等待时间的任务
+    Throttled,  //已获得令牌的等待任务
+    Exclusive,  //独占任务队列
+}
+
+impl TraceQueue {
+    //获取指定类型任务所在的任务队列
+    pub fn of(task_type: TaskType, is_delay: bool) -> Self {
+        match (task_type, is_delay) {
+            (TaskType::Exclusive, _) => TraceQueue::Exclusive,
+            (TaskType::Async, false) => TraceQueue::Async,
+            (TaskType::Async, true) => TraceQueue::AsyncDelay,
+            (_, false) => TraceQueue::Sync,
+            (_, true) => TraceQueue::SyncDelay,
+        }
+    }
+
+    //从编号构建
+    fn from_u8(n: u8) -> GenResult<Self, String> {
+        match n {
+            0 => Ok(TraceQueue::Sync),
+            1 => Ok(TraceQueue::Async),
+            2 => Ok(TraceQueue::SyncDelay),
+            3 => Ok(TraceQueue::AsyncDelay),
+            4 => Ok(TraceQueue::Overdue),
+            5 => Ok(TraceQueue::Throttled),
+            6 => Ok(TraceQueue::Exclusive),
+            _ => Err(format!("invalid trace queue, queue: {}", n)),
+        }
+    }
+}
+
+/*
+* 调度事件
+*/
+#[derive(Debug, Clone)]
+pub enum TraceEvent {
+    Push {                          //任务进入调度，定时任务在到达执行时间后记录
+        uid:        usize,          //任务唯一id
+        task_type:  TaskType,       //任务类型
+        priority:   u64,            //任务优先级
+        info:       Atom,           //任务信息
+        serial:     Option<Atom>,   //串行任务的键
+        tags:       Vec<Atom>,      //任务标签
+        sheddable:  bool,           //是否可以在过载时丢弃
+    },
+    Pop {                           //任务被弹出
+        uid:        usize,          //任务唯一id
+        queue:      TraceQueue,     //选择的队列
+        draws:      Vec<u64>,       //弹出时生成的随机数
+        worker:     Option<u32>,    //弹出任务的工作者编号，不是工作者线程为None
+    },
+    Complete {                      //已弹出的任务完成
+        uid:        usize,          //任务唯一id
+    },
+    Cancel {                        //任务被取消、驱逐、移除或丢弃，不会再被执行
+        uid:        usize,          //任务唯一id
+    },
+}
+
+impl TraceEvent {
+    //构建指定任务的进入调度事件
+    pub fn push<T>(task: &Task<T>) -> Self {
+        TraceEvent::Push {
+            uid:        task.get_uid(),
+            task_type:  task.get_type(),
+            priority:   task.get_priority(),
+            info:       task.get_info_atom().clone(),
+            serial:     task.get_serial().cloned(),
+            tags:       task.get_tags().to_vec(),
+            sheddable:  task.is_sheddable(),
+        }
+    }
+
+    //获取事件的任务唯一id
+    pub fn get_uid(&self) -> usize {
+        match *self {
+            TraceEvent::Push { uid, .. } => uid,
+            TraceEvent::Pop { uid, .. } => uid,
+            TraceEvent::Complete { uid } => uid,
+            TraceEvent::Cancel { uid } => uid,
+        }
+    }
+
+    //编码到指定缓冲区
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match *self {
+            TraceEvent::Push { uid, task_type, priority, ref info, ref serial, ref tags, sheddable } => {
+                buf.push(TRACE_PUSH);
+                write_varint(buf, uid as u64);
+                buf.push(task_type as u8);
+                write_varint(buf, priority);
+                write_str(buf, info);
+                match *serial {
+                    Some(ref key) => {
+                        buf.push(1);
+                        write_str(buf, key);
+                    },
+                    None => buf.push(0),
+                }
+                write_varint(buf, tags.len() as u64);
+                for tag in tags.iter() {
+                    write_str(buf, tag);
+                }
+                buf.push(sheddable as u8);
+            },
+            TraceEvent::Pop { uid, queue, ref draws, worker } => {
+                buf.push(TRACE_POP);
+                write_varint(buf, uid as u64);
+                buf.push(queue as u8);
+                write_varint(buf, draws.len() as u64);
+                for &draw in draws.iter() {
+                    write_varint(buf, draw);
+                }
+                write_varint(buf, match worker {
+                    Some(w) => w as u64 + 1,
+                    None => 0,
+                });
+            },
+            TraceEvent::Complete { uid } => {
+                buf.push(TRACE_COMPLETE);
+                write_varint(buf, uid as u64);
+            },
+            TraceEvent::Cancel { uid } => {
+                buf.push(TRACE_CANCEL);
+                write_varint(buf, uid as u64);
+            },
+        }
+    }
+
+    //从指定缓冲区解码，缓冲区为空返回None
+    fn decode(buf: &[u8], pos: &mut usize) -> GenResult<Option<Self>, String> {
+        if *pos >= buf.len() {
+            return Ok(None);
+        }
+
+        let tag = read_u8(buf, pos)?;
+        let event = match tag {
+            TRACE_PUSH => {
+                let uid = read_varint(buf, pos)? as usize;
+                let task_type = match read_u8(buf, pos)? {
+                    0 => TaskType::Empty,
+                    1 => TaskType::Async,
+                    2 => TaskType::Sync,
+                    3 => TaskType::SyncImme,
+                    4 => TaskType::Exclusive,
+                    n => return Err(format!("invalid trace task type, task_type: {}", n)),
+                };
+                let priority = read_varint(buf, pos)?;
+                let info = read_str(buf, pos)?;
+                let serial = match read_u8(buf, pos)? {
+                    0 => None,
+                    _ => Some(read_str(buf, pos)?),
+                };
+                let len = read_varint(buf, pos)?;
+                let mut tags = Vec::new();
+                for _ in 0..len {
+                    tags.push(read_str(buf, pos)?);
+                }
+                let sheddable = read_u8(buf, pos)? != 0;
+                TraceEvent::Push {
+                    uid:        uid,
+                    task_type:  task_type,
+                    priority:   priority,
+                    info:       info,
+                    serial:     serial,
+                    tags:       tags,
+                    sheddable:  sheddable,
+                }
+            },
+            TRACE_POP => {
+                let uid = read_varint(buf, pos)? as usize;
+                let queue = TraceQueue::from_u8(read_u8(buf, pos)?)?;
+                let len = read_varint(buf, pos)?;
+                let mut draws = Vec::new();
+                for _ in 0..len {
+                    draws.push(read_varint(buf, pos)?);
+                }
+                let worker = match read_varint(buf, pos)? {
+                    0 => None,
+                    w => Some((w - 1) as u32),
+                };
+                TraceEvent::Pop {
+                    uid:        uid,
+                    queue:      queue,
+                    draws:      draws,
+                    worker:     worker,
+                }
+            },
+            TRACE_COMPLETE => TraceEvent::Complete { uid: read_varint(buf, pos)? as usize },
+            TRACE_CANCEL => TraceEvent::Cancel { uid: read_varint(buf, pos)? as usize },
+            _ => return Err(format!("invalid trace event, tag: {}, pos: {}", tag, *pos - 1)),
+        };
+        Ok(Some(event))
+    }
+}
+
+/*
+* 调度轨迹输出，按批次序号顺序写入，先到达的后续批次等待之前的批次写入，写入失败后停止写入
+*/
+struct TraceOutput {
+    writer: Box<Write + Send>,      //轨迹输出
+    next:   u64,                    //下一个写入的批次序号
+    waits:  BTreeMap<u64, Vec<u8>>, //等待之前的批次写入的批次
+    error:  Option<String>,         //写入错误
+}
+
+impl TraceOutput {
+    //写入指定序号的批次，并写入之后已到达的批次
+    fn write(&mut self, seq: u64, buf: Vec<u8>) {
+        self.waits.insert(seq, buf);
+        while let Some(buf) = self.waits.remove(&self.next) {
+            self.next += 1;
+            if self.error.is_some() {
+                continue;
+            }
+
+            if let Err(e) = self.writer.write_all(&buf[..]) {
+                self.error = Some(format!("write trace event failed, e: {}", e));
+            }
+        }
+    }
+}
+
+/*
+* 调度轨迹批次，由任务池在持有锁时从记录器中取出，在任务池锁外释放时写入输出，避免在任务池锁内进行文件读写
+*/
+pub struct TraceBatch {
+    output: Arc<(Mutex<TraceOutput>, Condvar)>, //轨迹输出
+    seq:    u64,                                //批次序号
+    buf:    Vec<u8>,                            //已编码的事件
+}
+
+impl Drop for TraceBatch {
+    fn drop(&mut self) {
+        let &(ref lock, ref cvar) = &*self.output;
+        let buf = mem::replace(&mut self.buf, Vec::new());
+        lock.lock().unwrap().write(self.seq, buf);
+        cvar.notify_all(); //唤醒等待之前的批次写入的刷新者
+    }
+}
+
+/*
+* 调度轨迹记录器，由任务池在持有锁时记录调度事件到缓冲区，缓冲区中的事件作为批次由任务池的take_released取出，在任务池锁外写入
+*/
+pub struct TraceRecorder {
+    output: Arc<(Mutex<TraceOutput>, Condvar)>, //轨迹输出
+    buf:    Vec<u8>,                            //未取出的已编码事件
+    seq:    u64,                                //下一个批次序号
+    count:  u64,                                //已记录的事件数量
+}
+
+impl Display for TraceRecorder {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "TraceRecorder[count = {}, seq = {}, buf_size = {}]", self.count, self.seq, self.buf.len())
+	}
+}
+
+impl TraceRecorder {
+    //构建一个写入指定输出的调度轨迹记录器
+    pub fn new(mut writer: Box<Write + Send>) -> GenResult<Self, String> {
+        let mut header = TRACE_MAGIC.to_vec();
+        header.push(TRACE_VERSION);
+        if let Err(e) = writer.write_all(&header[..]) {
+            return Err(format!("write trace header failed, e: {}", e));
+        }
+        let output = TraceOutput {
+            writer: writer,
+            next:   0,
+            waits:  BTreeMap::new(),
+            error:  None,
+        };
+        Ok(TraceRecorder {
+            output: Arc::new((Mutex::new(output), Condvar::new())),
+            buf:    Vec::new(),
+            seq:    0,
+            count:  0,
+        })
+    }
+
+    //构建一个写入指定文件的调度轨迹记录器，文件已存在则覆盖
+    pub fn create<P: AsRef<Path>>(path: P) -> GenResult<Self, String> {
+        match File::create(path.as_ref()) {
+            Err(e) => Err(format!("create trace file failed, path: {:?}, e: {}", path.as_ref(), e)),
+            Ok(file) => TraceRecorder::new(Box::new(BufWriter::new(file))),
+        }
+    }
+
+    //获取已记录的事件数量
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    //获取写入错误
+    pub fn get_error(&self) -> Option<String> {
+        let &(ref lock, _) = &*self.output;
+        lock.lock().unwrap().error.clone()
+    }
+
+    //记录一个调度事件到缓冲区，不写入输出
+    pub fn record(&mut self, event: &TraceEvent) {
+        event.encode(&mut self.buf);
+        self.count += 1;
+    }
+
+    //取出缓冲区中的事件作为批次，没有事件返回None，批次释放时按取出的顺序写入输出
+    pub fn take_batch(&mut self) -> Option<TraceBatch> {
+        if self.buf.is_empty() {
+            return None;
+        }
+
+        let batch = TraceBatch {
+            output: self.output.clone(),
+            seq:    self.seq,
+            buf:    mem::replace(&mut self.buf, Vec::new()),
+        };
+        self.seq += 1;
+        Some(batch)
+    }
+
+    //将缓冲区中的事件写入输出，并等待已取出的批次都写入后刷新输出，不应在任务池锁内调用
+    pub fn flush(&mut self) -> GenResult<(), String> {
+        drop(self.take_batch());
+
+        let &(ref lock, ref cvar) = &*self.output;
+        let mut output = lock.lock().unwrap();
+        while output.next < self.seq {
+            output = cvar.wait(output).unwrap();
+        }
+        if let Some(ref e) = output.error {
+            return Err(e.clone());
+        }
+
+        match output.writer.flush() {
+            Err(e) => Err(format!("flush trace failed, e: {}", e)),
+            Ok(_) => Ok(()),
+        }
+    }
+}
+
+/*
+* 从指定输入读取调度轨迹
+*/
+pub fn read_trace<R: Read>(mut reader: R) -> GenResult<Vec<TraceEvent>, String> {
+    let mut buf = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut buf) {
+        return Err(format!("read trace failed, e: {}", e));
+    }
+
+    let len = TRACE_MAGIC.len();
+    if buf.len() <= len || &buf[..len] != TRACE_MAGIC {
+        return Err(format!("invalid trace header"));
+    }
+    if buf[len] != TRACE_VERSION {
+        return Err(format!("invalid trace version, version: {}", buf[len]));
+    }
+
+    let mut pos = len + 1;
+    let mut events = Vec::new();
+    while let Some(event) = TraceEvent::decode(&buf[..], &mut pos)? {
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/*
+* 从指定文件读取调度轨迹
+*/
+pub fn load_trace<P: AsRef<Path>>(path: P) -> GenResult<Vec<TraceEvent>, String> {
+    match File::open(path.as_ref()) {
+        Err(e) => Err(format!("open trace file failed, path: {:?}, e: {}", path.as_ref(), e)),
+        Ok(file) => read_trace(BufReader::new(file)),
+    }
+}
+
+/*
+* 调度轨迹重放器，在当前线程中按轨迹驱动任务池，弹出时使用轨迹中记录的随机数，并校验弹出的任务与轨迹一致，
+* 定时、老化、限速和过载丢弃等依赖时间的行为不会被重放，需要重放时任务池应使用与记录时相同的调度策略，且不设置这些配置
+*/
+pub struct Replayer {
+    events: Vec<TraceEvent>,    //调度轨迹
+    seed:   u64,                //任务池的随机数种子，轨迹中的随机数不足时使用
+}
+
+impl Display for Replayer {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "Replayer[event_size = {}, seed = {}]", self.events.len(), self.seed)
+	}
+}
+
+impl Replayer {
+    //构建一个重放指定调度轨迹的重放器
+    pub fn new(events: Vec<TraceEvent>) -> Self {
+        Replayer {
+            events: events,
+            seed:   0,
+        }
+    }
+
+    //构建一个重放指定文件中调度轨迹的重放器
+    pub fn load<P: AsRef<Path>>(path: P) -> GenResult<Self, String> {
+        Ok(Replayer::new(load_trace(path)?))
+    }
+
+    //获取调度轨迹
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events[..]
+    }
+
+    //设置任务池的随机数种子
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    //使用指定的任务池重放，由payload根据任务信息构建任务负载，由run执行弹出的任务，返回按执行顺序排列的记录时的任务唯一id，
+    //弹出的任务与轨迹不一致则返回错误
+    pub fn replay<T, F, R>(&self, pool: &mut TaskPool<T>, mut payload: F, mut run: R) -> GenResult<Vec<usize>, String>
+        where F: FnMut(&Atom) -> T, R: FnMut(&mut Task<T>) {
+        pool.set_seed(self.seed);
+        let mut uids: FnvHashMap<usize, usize> = FnvHashMap::default(); //记录时的任务唯一id对应重放时的任务唯一id
+        let mut running: FnvHashMap<usize, Task<T>> = FnvHashMap::default();
+        let mut order = Vec::new();
+        for (index, event) in self.events.iter().enumerate() {
+            match *event {
+                TraceEvent::Push { uid, task_type, priority, ref info, ref serial, ref tags, sheddable } => {
+                    let p = payload(info);
+                    let r = match *serial {
+                        Some(ref key) => pool.push_serial(key.clone(), task_type, priority, p, info.clone()),
+                        None => pool.push_with(task_type, priority, p, info.clone(), tags.clone(), sheddable),
+                    };
+                    match r {
+                        Err(e) => return Err(format!("replay push failed, index: {}, uid: {}, e: {}", index, uid, e)),
+                        Ok(u) => {
+                            uids.insert(uid, u);
+                        },
+                    }
+                },
+                TraceEvent::Pop { uid, ref draws, .. } => {
+                    let expect = match uids.get(&uid) {
+                        Some(&u) => u,
+                        None => return Err(format!("replay pop failed, task not pushed, index: {}, uid: {}", index, uid)),
+                    };
+                    pool.force_draws(&draws[..]);
+                    let mut task = Task::new();
+                    if !pool.pop(&mut task) {
+                        return Err(format!("replay diverged, pool empty, index: {}, uid: {}", index, uid));
+                    }
+                    if task.get_uid() != expect {
+                        return Err(format!("replay diverged, index: {}, uid: {}, expect: {}, popped: {}, info: {}",
+                            index, uid, expect, task.get_uid(), task.get_info()));
+                    }
+                    match self.events.get(index + 1) {
+                        Some(&TraceEvent::Cancel { uid: next }) if next == uid => (), //弹出后被丢弃的任务不执行
+                        _ => {
+                            order.push(uid);
+                            run(&mut task);
+                        },
+                    }
+                    running.insert(uid, task);
+                },
+                TraceEvent::Complete { uid } => {
+                    match running.remove(&uid) {
+                        Some(mut task) => {
+                            pool.complete(&mut task);
+                        },
+                        None => return Err(format!("replay complete failed, task not popped, index: {}, uid: {}", index, uid)),
+                    }
+                },
+                TraceEvent::Cancel { uid } => {
+                    match running.remove(&uid) {
+                        Some(mut task) => {
+                            //已弹出的任务被丢弃
+                            pool.complete(&mut task);
+                        },
+                        None => {
+                            //未进入调度的定时任务没有对应的任务
+                            if let Some(u) = uids.get(&uid) {
+                                pool.cancel(*u);
+                            }
+                        },
+                    }
+                },
+            }
+            pool.take_released(); //释放重放中被丢弃或驱逐的任务的任务函数
+        }
+        Ok(order)
+    }
+}
+
+//写入变长整数
+fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+//写入字符串
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    write_varint(buf, s.len() as u64);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+//读取一个字节
+fn read_u8(buf: &[u8], pos: &mut usize) -> GenResult<u8, String> {
+    if *pos >= buf.len() {
+        return Err(format!("trace truncated, pos: {}", *pos));
+    }
+
+    let n = buf[*pos];
+    *pos += 1;
+    Ok(n)
+}
+
+//读取变长整数
+fn read_varint(buf: &[u8], pos: &mut usize) -> GenResult<u64, String> {
+    let mut n: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let b = read_u8(buf, pos)?;
+        if shift >= 64 {
+            return Err(format!("invalid trace varint, pos: {}", *pos));
+        }
+        n |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok(n);
+        }
+        shift += 7;
+    }
+}
+
+//读取字符串
+fn read_str(buf: &[u8], pos: &mut usize) -> GenResult<Atom, String> {
+    let len = read_varint(buf, pos)? as usize;
+    if buf.len() - *pos < len {
+        return Err(format!("trace truncated, pos: {}", *pos));
+    }
+
+    let s = match String::from_utf8(buf[*pos..*pos + len].to_vec()) {
+        Err(e) => return Err(format!("invalid trace string, pos: {}, e: {}", *pos, e)),
+        Ok(s) => s,
+    };
+    *pos += len;
+    Ok(Atom::from(s))
+}
@@ -0,0 +1,132 @@
+use std::fmt::{Display, Formatter, Result};
+
+/*
+* 权重树，基于树状数组，支持O(logn)的追加、修改权重和按权重查找位置
+*/
+pub struct WeightTree {
+    weights:    Vec<u64>,   //各位置的权重
+    tree:       Vec<u64>,   //树状数组，tree[i]为位置(i - lowbit(i), i]的权重和，下标从1开始
+}
+
+impl Display for WeightTree {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "WeightTree[len = {}, total = {}]", self.len(), self.total())
+	}
+}
+
+impl WeightTree {
+    //构建一个权重树
+    pub fn new() -> Self {
+        WeightTree {
+            weights:    Vec::new(),
+            tree:       vec![0],
+        }
+    }
+
+    //获取位置数量
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    //获取权重总和
+    pub fn total(&self) -> u64 {
+        self.prefix(self.weights.len())
+    }
+
+    //获取指定位置的权重
+    pub fn get(&self, index: usize) -> u64 {
+        self.weights[index]
+    }
+
+    //在尾部追加一个指定权重的位置，返回位置
+    pub fn push(&mut self, weight: u64) -> usize {
+        let index = self.weights.len();
+        let i = index + 1;
+        //新节点覆盖(i - lowbit(i), i]，等于前缀和之差加上自身权重
+        let sum = weight + self.prefix(index) - self.prefix(i - lowbit(i));
+        self.weights.push(weight);
+        self.tree.push(sum);
+        index
+    }
+
+    //设置指定位置的权重
+    pub fn set(&mut self, index: usize, weight: u64) {
+        let old = self.weights[index];
+        if old == weight {
+            return;
+        }
+
+        self.weights[index] = weight;
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            if weight > old {
+                self.tree[i] += weight - old;
+            } else {
+                self.tree[i] -= old - weight;
+            }
+            i += lowbit(i);
+        }
+    }
+
+    //查找指定权重落在的位置，即前缀和大于指定权重的最小位置，权重不小于总和则返回None
+    pub fn find(&self, mut weight: u64) -> Option<usize> {
+        let len = self.tree.len() - 1;
+        let mut step = 1;
+        while step * 2 <= len {
+            step *= 2;
+        }
+
+        let mut pos = 0;
+        while step > 0 {
+            if pos + step <= len && self.tree[pos + step] <= weight {
+                pos += step;
+                weight -= self.tree[pos];
+            }
+            step /= 2;
+        }
+
+        if pos < len {
+            Some(pos)
+        } else {
+            None
+        }
+    }
+
+    //使用指定的权重序列重建
+    pub fn rebuild(&mut self, weights: Vec<u64>) {
+        let len = weights.len();
+        let mut tree = Vec::with_capacity(len + 1);
+        tree.push(0);
+        tree.extend_from_slice(&weights[..]);
+        for i in 1..(len + 1) {
+            let parent = i + lowbit(i);
+            if parent <= len {
+                tree[parent] += tree[i];
+            }
+        }
+        self.weights = weights;
+        self.tree = tree;
+    }
+
+    //清空
+    pub fn clear(&mut self) {
+        self.weights.clear();
+        self.tree.truncate(1);
+    }
+
+    //获取前len个位置的权重和
+    pub fn prefix(&self, len: usize) -> u64 {
+        let mut sum = 0;
+        let mut i = len;
+        while i > 0 {
+            sum += self.tree[i];
+            i -= lowbit(i);
+        }
+        sum
+    }
+}
+
+#[inline]
+fn lowbit(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::thread::park_timeout;
 use std::time::{Instant, Duration};
 use std::sync::{Arc, Mutex, Condvar};
@@ -7,7 +8,94 @@ use std::sync::atomic::{Ordering, AtomicUsize};
 use threadpool::ThreadPool;
 
 use task_pool::TaskPool;
-use task::Task;
+use task::{TaskType, Task};
+
+/*
+* 工作者没有可执行的任务时单次等待的最长时间，超时后处理控制状态，单位ms
+*/
+const WORKER_WAIT_TIMEOUT: u64 = 100;
+
+thread_local! {
+    static CURRENT_WORKER: Cell<Option<u32>> = Cell::new(None); //当前线程的工作者编号
+}
+
+/*
+* 任务池后端，工作者从任务池后端获取并执行任务
+*/
+pub trait TaskBackend: Send + Sync + 'static {
+    //工作者在当前线程开始工作时调用
+    fn attach(&self, _worker: u32) {}
+
+    //工作者在当前线程停止工作时调用
+    fn detach(&self, _worker: u32) {}
+
+    //获取一个任务并填充到指定任务中，没有任务则等待一段时间，获取到任务返回true，未获取到任务则返回false，以便工作者处理控制状态
+    fn acquire(&self, task: &mut Task) -> bool;
+
+    //指定任务执行完成后调用
+    fn complete(&self, _task: &mut Task) {}
+}
+
+impl TaskBackend for (Mutex<TaskPool>, Condvar) {
+    fn attach(&self, _worker: u32) {
+        let &(ref lock, _) = self;
+        lock.lock().unwrap().attach_worker();
+    }
+
+    fn detach(&self, _worker: u32) {
+        let &(ref lock, _) = self;
+        lock.lock().unwrap().detach_worker();
+    }
+
+    fn acquire(&self, task: &mut Task) -> bool {
+        let &(ref lock, ref cvar) = self;
+        let (popped, released) = {
+            let mut task_pool = lock.lock().unwrap();
+            (*task_pool).poll_timed(); //将已到执行时间的定时任务加入任务队列
+            let mut popped = true;
+            while !(*task_pool).pop(task) {
+                //没有可执行的任务，包括任务都因没有令牌而等待，则等待任务加入，或等待到最近的定时任务执行时间或等待令牌的任务获得令牌的时间
+                let now = (*task_pool).now();
+                let (timeout, is_deadline) = match (*task_pool).next_deadline() {
+                    Some(time) if time <= now => (Duration::new(0, 0), true),
+                    Some(time) if time - now < Duration::from_millis(WORKER_WAIT_TIMEOUT) => (time - now, true),
+                    _ => (Duration::from_millis(WORKER_WAIT_TIMEOUT), false),
+                };
+                let (pool, wait) = cvar.wait_timeout(task_pool, timeout).unwrap();
+                task_pool = pool;
+                if wait.timed_out() && !is_deadline {
+                    popped = false; //等待超时，则立即解锁，并处理控制状态
+                    break;
+                }
+                (*task_pool).poll_timed();
+            }
+            if popped && (*task_pool).blocked_size() > 0 {
+                //唤醒等待任务池空间的调用者
+                cvar.notify_all();
+            }
+            (popped, (*task_pool).take_released())
+        };
+        drop(released); //在任务池锁外释放被丢弃的任务的任务函数
+        popped
+    }
+
+    fn complete(&self, task: &mut Task) {
+        let &(ref lock, ref cvar) = self;
+        let mut task_pool = lock.lock().unwrap();
+        if (*task_pool).complete(task) {
+            if let TaskType::Exclusive = task.get_type() {
+                //独占任务执行完成后恢复分派，唤醒所有等待任务的工作者
+                cvar.notify_all();
+            } else {
+                //串行任务执行完成后，需要在任务池中继续同一个键的下一个任务，或所有任务都已结束，需要执行等待中的独占任务
+                cvar.notify_one();
+            }
+        } else if (*task_pool).is_idle() {
+            //任务池空闲，则唤醒所有等待任务池空闲的调用者
+            cvar.notify_all();
+        }
+    }
+}
 
 /*
 * 工作者状态
@@ -51,16 +139,20 @@ impl Worker {
     }
 
     //启动
-    pub fn startup(pool: &ThreadPool, worker: Arc<Worker>, sync: Arc<(Mutex<TaskPool>, Condvar)>) -> bool {
+    pub fn startup<P: TaskBackend>(pool: &ThreadPool, worker: Arc<Worker>, sync: Arc<P>) -> bool {
         pool.execute(move|| {
             let mut task = Task::new();
-            Worker::work_loop(worker, sync, &mut task);
+            CURRENT_WORKER.with(|current| current.set(Some(worker.uid)));
+            sync.attach(worker.uid);
+            Worker::work_loop(worker.clone(), sync.clone(), &mut task);
+            sync.detach(worker.uid);
+            CURRENT_WORKER.with(|current| current.set(None));
         });
         true
     }
 
     //工作循环
-    fn work_loop(worker: Arc<Worker>, sync: Arc<(Mutex<TaskPool>, Condvar)>, task: &mut Task) {
+    fn work_loop<P: TaskBackend>(worker: Arc<Worker>, sync: Arc<P>, task: &mut Task) {
         let mut status: usize;
         loop {
             status = worker.get_status();
@@ -117,36 +209,41 @@ impl Worker {
     }
 
     //工作
-    fn work(&self, sync: &Arc<(Mutex<TaskPool>, Condvar)>, task: &mut Task) {
-        //同步块
-        {
-            let &(ref lock, ref cvar) = &**sync;
-            let mut task_pool = lock.lock().unwrap();
-            while (*task_pool).size() == 0 {
-                //等待任务
-                let (pool, wait) = cvar.wait_timeout(task_pool, Duration::from_micros(1000)).unwrap();
-                if wait.timed_out() {
-                    return //等待超时，则立即解锁，并处理控制状态
-                }
-                task_pool = pool;
-            }
-            (*task_pool).pop(task); //获取任务
+    fn work<P: TaskBackend>(&self, sync: &Arc<P>, task: &mut Task) {
+        if !sync.acquire(task) {
+            return //没有获取到任务，则处理控制状态
         }
         check_slow_task(self, task); //执行任务
         self.counter.fetch_add(1, Ordering::Acquire); //增加工作计数
+        sync.complete(task);
     }
 }
 
+//获取当前线程的工作者编号，不是工作者线程返回None
+pub fn current_worker() -> Option<u32> {
+    CURRENT_WORKER.with(|current| current.get())
+}
+
 #[inline]
 fn check_slow_task(worker: &Worker, task: &mut Task) {
     use std::panic;
+    let observer = task.get_observer().cloned();
+    if let Some(ref o) = observer {
+        o.get_observer().on_start(&o.event(task, Some(worker.uid)));
+    }
     let time = Instant::now();
-    if let Err(e) = panic::catch_unwind(|| { task.run(); }) {
+    if let Err(e) = panic::catch_unwind(panic::AssertUnwindSafe(|| { task.run(); })) {
         //执行任务失败
+        if let Some(ref o) = observer {
+            o.get_observer().on_panic(&o.event(task, Some(worker.uid)));
+        }
         let elapsed = time.elapsed();
         println!("!!!> Task Run Error, time: {}, task: {}, e: {:?}", elapsed.as_secs() * 1000000 + (elapsed.subsec_micros() as u64), task, e);
     } else {
         //执行任务成功
+        if let Some(ref o) = observer {
+            o.get_observer().on_finish(&o.event(task, Some(worker.uid)));
+        }
         let elapsed = time.elapsed();
         if time.elapsed() >= worker.slow {
             //记录慢任务
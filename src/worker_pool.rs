@@ -1,11 +1,10 @@
 use fnv::FnvHashMap;
-use std::sync::{Arc, Mutex, Condvar};
+use std::sync::Arc;
 use std::fmt::{Display, Formatter, Result as FmtResult}; //避免和标准Result冲突，改名为FmtResult
 
 use threadpool::{ThreadPool, Builder as ThreadPoolBuilder};
 
-use task_pool::TaskPool;
-use worker::{WorkerStatus, Worker};
+use worker::{WorkerStatus, Worker, TaskBackend};
 
 /*
 * 工作者池
@@ -89,8 +88,8 @@ impl WorkerPool {
         }
     }
 
-    //启动工作者，启动时需要指定任务池的同步对象
-    pub fn start(&self, sync: Arc<(Mutex<TaskPool>, Condvar)>, uid: u32) -> bool {
+    //启动工作者，启动时需要指定任务池的同步对象，任务池可以是互斥任务池或工作窃取任务池
+    pub fn start<P: TaskBackend>(&self, sync: Arc<P>, uid: u32) -> bool {
         match self.map.get(&uid) {
             Some(worker) => {
                 if worker.set_status(WorkerStatus::Stop, WorkerStatus::Running) {
@@ -103,8 +102,8 @@ impl WorkerPool {
         }
     }
 
-    //在指定任务池中，运行工作池，需要指定任务池的同步对象
-    pub fn run(&self, sync: Arc<(Mutex<TaskPool>, Condvar)>) {
+    //在指定任务池中，运行工作池，需要指定任务池的同步对象，任务池可以是互斥任务池或工作窃取任务池
+    pub fn run<P: TaskBackend>(&self, sync: Arc<P>) {
         for (_, worker) in self.map.iter() {
             if worker.set_status(WorkerStatus::Wait, WorkerStatus::Running) {
                 Worker::startup(&self.thread_pool, worker.clone(), sync.clone());
@@ -113,7 +112,7 @@ impl WorkerPool {
     }
 
     //增加工作者
-    pub fn increase(&mut self, sync: Arc<(Mutex<TaskPool>, Condvar)>, len: usize, slow: u32) {
+    pub fn increase<P: TaskBackend>(&mut self, sync: Arc<P>, len: usize, slow: u32) {
         if len == 0 {
             return;
         }
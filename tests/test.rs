@@ -9,19 +9,37 @@ extern crate pi_base;
 use std::thread;
 use std::sync::Arc;
 use std::boxed::FnBox;
-use std::path::PathBuf;
 use std::time::Duration;
+use std::sync::{Mutex, Condvar};
+use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering};
 use std::result::Result as NormalResult;
-use std::io::{Error, Result};
+use std::io::{Error, Result, Write};
 
 use futures::*;
 use npnc::bounded::mpmc::{Producer, Consumer};
+use npnc::bounded::spsc::Producer as SpscProducer;
 
 use pi_lib::atom::Atom;
-use pi_base::task::TaskType;
-use pi_base::pi_base_impl::{STORE_TASK_POOL, EXT_TASK_POOL, cast_ext_task};
+use pi_base::task::{TaskType, Task};
+use pi_base::task_pool::{TaskPool, SyncPool, AsyncPool, FullPolicy, AgingConfig, PushErrorKind, push_task, wait_idle};
+use pi_base::policy::{SchedulingPolicy, PolicyRng, StrictPriorityPolicy, WeightedRoundRobinPolicy, EarliestDeadlinePolicy};
+use pi_base::weight_tree::WeightTree;
+use pi_base::steal_pool::StealPool;
+use pi_base::task_group::{TaskGroup, GroupStatus};
+use pi_base::task_graph::{TaskGraph, NodeStatus};
+use pi_base::task_handle::spawn;
+use pi_base::scope::scope;
+use pi_base::par::{par_for_each, par_map, par_reduce};
+use pi_base::continuation::{Resume, push_resumable, push_resumable_with, push_sliced};
+use pi_base::executor::TestExecutor;
+use pi_base::context::{TaskContext, current, set_current};
+use pi_base::pi_base_impl::{PoolBackend, JS_TASK_POOL, EXT_TASK_POOL, cancel_js_task, cast_ext_task, cast_js_task, cast_store_task, install_js_executor, install_store_executor, set_js_backend};
+use pi_base::worker::WorkerStatus;
+use pi_base::observer::{TaskObserver, TaskEvent, PoolObserver};
+use pi_base::histogram::Histogram;
+use pi_base::trace::{TraceEvent, TraceRecorder, Replayer, read_trace};
 use pi_base::worker_pool::WorkerPool;
-use pi_base::file::{Shared, AsyncFile, AsynFileOptions, WriteOptions};
+use pi_base::file::{Shared, SharedFile, AsyncFile, AsynFileOptions, WriteOptions};
 use pi_base::util::{CompressLevel, compress, uncompress};
 use pi_base::future_pool::FutTaskPool;
 use pi_base::fs_monitor::{FSMonitorOptions, FSListener, FSMonitor};
@@ -45,153 +63,1612 @@ fn test_lz4() {
     assert!(String::from_utf8(vec_).ok().unwrap() == string);
 }
 
-// #[test]
+#[test]
 fn test_file() {
-	let worker_pool = Box::new(WorkerPool::new(10, 1024 * 1024, 10000));
-    worker_pool.run(STORE_TASK_POOL.clone());
+	//存储任务池安装测试执行器后，异步文件的任务由执行器在当前线程执行，不需要运行工作者池和等待
+	let mut executor = install_store_executor(0).unwrap();
+	let dir = std::env::temp_dir();
+	let path = dir.join("pi_base_test_file.txt");
+	let swap = dir.join("pi_base_test_file.swap");
+	let content = "Hello World!!!!!!######你好 Rust\nHello World!!!!!!######你好 Rust\nHello World!!!!!!######你好 Rust\n";
+	let results = Arc::new(Mutex::new(Vec::new()));
 
+	//打开文件并写入
+	let results_copy = results.clone();
 	let open = move |f0: Result<AsyncFile>| {
 		assert!(f0.is_ok());
 		let write = move |f1: AsyncFile, result: Result<()>| {
 			assert!(result.is_ok());
-			println!("!!!!!!write file");
-			let write = move |_f3: AsyncFile, result: Result<()>| {
+			let write = move |_f2: AsyncFile, result: Result<()>| {
 				assert!(result.is_ok());
-				println!("!!!!!!write file by sync");
+				results_copy.lock().unwrap().push(("write", Vec::new()));
 			};
-			f1.write(WriteOptions::SyncAll(true), 8, Vec::from("Hello World!!!!!!######你好 Rust\nHello World!!!!!!######你好 Rust\nHello World!!!!!!######你好 Rust\n".as_bytes()), Box::new(write));
+			f1.write(WriteOptions::SyncAll(true), 8, Vec::from(content.as_bytes()), Box::new(write));
 		};
 		f0.ok().unwrap().write(WriteOptions::Flush, 0, vec![], Box::new(write));
 	};
-	AsyncFile::open(PathBuf::from(r"foo.txt"), AsynFileOptions::ReadWrite(1), Box::new(open));
-	thread::sleep(Duration::from_millis(5000));
+	AsyncFile::open(path.clone(), AsynFileOptions::ReadWrite(1), Box::new(open));
+	assert!(executor.run_until_idle() > 0);
 
+	//只读打开文件并读取
+	let results_copy = results.clone();
 	let open = move |f0: Result<AsyncFile>| {
 		assert!(f0.is_ok());
-		println!("!!!!!!open file, symlink: {}, file: {}, only_read: {}, size: {}, time: {:?}", 
-			f0.as_ref().ok().unwrap().is_symlink(), f0.as_ref().ok().unwrap().is_file(), f0.as_ref().ok().unwrap().is_only_read(), 
-			f0.as_ref().ok().unwrap().get_size(), 
-			(f0.as_ref().ok().unwrap().get_modified_time(), f0.as_ref().ok().unwrap().get_accessed_time(), f0.as_ref().ok().unwrap().get_created_time()));
+		let f0 = f0.ok().unwrap();
+		assert!(f0.is_file() && !f0.is_only_read() && f0.get_size() == 8 + content.len() as u64);
 		let read = move |f1: AsyncFile, result: Result<Vec<u8>>| {
-			assert!(result.is_ok());
-			println!("!!!!!!read file1, result: {:?}", result.ok().unwrap());
-			let read = move |f3: AsyncFile, result: Result<Vec<u8>>| {
-				assert!(result.is_ok());
-				println!("!!!!!!read file3, result: {:?}", String::from_utf8(result.ok().unwrap()).unwrap_or("invalid utf8 string".to_string()));
-				let read = move |f4: AsyncFile, result: Result<Vec<u8>>| {
-					assert!(result.is_ok());
-					println!("!!!!!!read file4, result: {:?}", String::from_utf8(result.ok().unwrap()).unwrap_or("invalid utf8 string".to_string()));
-					let read = move |f7: AsyncFile, result: Result<Vec<u8>>| {
-						assert!(result.is_ok());
-						println!("!!!!!!read file7, result: {:?}", String::from_utf8(result.ok().unwrap()).unwrap_or("invalid utf8 string".to_string()));
-						let read = move |f11: AsyncFile, result: Result<Vec<u8>>| {
-							assert!(result.is_ok());
-							println!("!!!!!!read file11, result: {:?}", String::from_utf8(result.ok().unwrap()).unwrap_or("invalid utf8 string".to_string()));
-							let read = move |_f13: AsyncFile, result: Result<Vec<u8>>| {
-								assert!(result.is_ok());
-								println!("!!!!!!read file13, result: {:?}", String::from_utf8(result.ok().unwrap()).unwrap_or("invalid utf8 string".to_string()));
-								
-							};
-							f11.read(0, 1000, Box::new(read));
-						};
-						f7.read(0, 34, Box::new(read));
-					};
-					f4.read(0, 32, Box::new(read));
-				};
-				f3.read(0, 16, Box::new(read));
+			results_copy.lock().unwrap().push(("read empty", result.ok().unwrap()));
+			let read = move |_f2: AsyncFile, result: Result<Vec<u8>>| {
+				results_copy.lock().unwrap().push(("read", result.ok().unwrap()));
 			};
-			f1.read(0, 10, Box::new(read));
+			f1.read(8, content.len(), Box::new(read));
 		};
-		f0.ok().unwrap().read(0, 0, Box::new(read));
+		f0.read(0, 0, Box::new(read));
 	};
-	AsyncFile::open(PathBuf::from(r"foo.txt"), AsynFileOptions::OnlyRead(1), Box::new(open));
-	thread::sleep(Duration::from_millis(1000));
+	AsyncFile::open(path.clone(), AsynFileOptions::OnlyRead(1), Box::new(open));
+	assert!(executor.run_until_idle() > 0);
 
-	let rename = move |from, to, result: Result<()>| {
+	//重命名并移除文件
+	let results_copy = results.clone();
+	let rename = move |_from, to, result: Result<()>| {
 		assert!(result.is_ok());
-		println!("!!!!!!rename file, from: {:?}, to: {:?}", from, to);
-
 		let remove = move |result: Result<()>| {
 			assert!(result.is_ok());
-			println!("!!!!!!remove file");
+			results_copy.lock().unwrap().push(("remove", Vec::new()));
 		};
-		AsyncFile::remove(PathBuf::from(r"foo.swap"), Box::new(remove));
+		AsyncFile::remove(to, Box::new(remove));
 	};
-	AsyncFile::rename(PathBuf::from(r"foo.txt"), PathBuf::from(r"foo.swap"), Box::new(rename));
-	thread::sleep(Duration::from_millis(1000));
-}
-
-// #[test]
-fn test_shared_file() {
-	let worker_pool = Box::new(WorkerPool::new(10, 1024 * 1024, 10000));
-    worker_pool.run(STORE_TASK_POOL.clone());
+	AsyncFile::rename(path.clone(), swap.clone(), Box::new(rename));
+	assert!(executor.run_until_idle() > 0);
+	assert!(!path.exists() && !swap.exists());
+	assert!(*results.lock().unwrap() == vec![("write", Vec::new()), ("read empty", Vec::new()), ("read", Vec::from(content.as_bytes())), ("remove", Vec::new())]);
 
+	//共享文件按顺序原子的写入和读取
+	let path = dir.join("pi_base_test_shared_file.txt");
+	let results = Arc::new(Mutex::new(Vec::new()));
+	let results_copy = results.clone();
 	let open = move |f0: Result<AsyncFile>| {
 		assert!(f0.is_ok());
-		let shared = Arc::new(f0.ok().unwrap());
-		let f0 = shared.clone();
-		let f1 = shared.clone();
-		let f3 = shared.clone();
-
-		thread::spawn(move || {
-			let write = move |shared0: Arc<AsyncFile>, result: Result<usize>| {
-				assert!(result.is_ok() && result.ok() == Some(0));
-				let write = move |_shared1: Arc<AsyncFile>, result: Result<usize>| {
-					assert!(result.is_ok() && result.ok() == Some(105));
+		let shared: SharedFile = Arc::new(f0.ok().unwrap());
+		let write = move |f1: SharedFile, result: Result<usize>| {
+			assert!(result.ok() == Some(105));
+			let write = move |f2: SharedFile, result: Result<usize>| {
+				assert!(result.ok() == Some(137));
+				let read = move |f3: SharedFile, result: Result<Vec<u8>>| {
+					results_copy.lock().unwrap().push(result.ok().unwrap());
+					let write = move |f4: SharedFile, result: Result<usize>| {
+						assert!(result.ok() == Some(13));
+						let read = move |_f5: SharedFile, result: Result<Vec<u8>>| {
+							results_copy.lock().unwrap().push(result.ok().unwrap());
+						};
+						f4.fpread(vec![255; 3], 3, 250, 13, Box::new(read));
+					};
+					f3.pwrite(WriteOptions::SyncAll(true), 250, Vec::from("\nHello Rust0\n".as_bytes()), Box::new(write));
 				};
-				shared0.pwrite(WriteOptions::SyncAll(true), 8, Vec::from("Hello World!!!!!!######你好 Rust\nHello World!!!!!!######你好 Rust\nHello World!!!!!!######你好 Rust\n".as_bytes()), Box::new(write));
+				f2.pread(0, 250, Box::new(read));
 			};
-			shared.pwrite(WriteOptions::Flush, 0, vec![], Box::new(write));
-		});
+			f1.pwrite(WriteOptions::SyncAll(true), 113, Vec::from("HelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHello\n\n".as_bytes()), Box::new(write));
+		};
+		shared.pwrite(WriteOptions::SyncAll(true), 8, Vec::from(content.as_bytes()), Box::new(write));
+	};
+	AsyncFile::open(path.clone(), AsynFileOptions::ReadWrite(1), Box::new(open));
+	assert!(executor.run_until_idle() > 0);
+	{
+		let results = results.lock().unwrap();
+		assert!(results.len() == 2 && results[0].len() == 250 && &results[0][8..113] == content.as_bytes());
+		let mut expect = vec![255; 3];
+		expect.extend_from_slice("\nHello Rust0\n".as_bytes());
+		assert!(results[1] == expect);
+	}
+	AsyncFile::remove(path.clone(), Box::new(|result: Result<()>| assert!(result.is_ok())));
+	assert!(executor.run_until_idle() == 1 && !path.exists());
 
-		thread::spawn(move || {
-			let write = move |f00: Arc<AsyncFile>, result: Result<usize>| {
-				assert!(result.is_ok() && result.ok() == Some(0));
-				let write = move |_f01: Arc<AsyncFile>, result: Result<usize>| {
-					assert!(result.is_ok() && result.ok() == Some(137));
-				};
-				f00.pwrite(WriteOptions::SyncAll(true), 113, Vec::from("HelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHelloHello\n\n".as_bytes()), Box::new(write));
-			};
-			f0.pwrite(WriteOptions::Flush, 0, vec![], Box::new(write));
+	//未来任务由执行器执行后即可获取结果
+	let future = FutTaskPool::new(cast_store_task).spawn::<usize, String>(Box::new(move |_, producer: Arc<SpscProducer<NormalResult<usize, String>>>, _, uid| {
+		producer.produce(Ok(uid + 100)).unwrap();
+	}), 1000).unwrap();
+	let uid = future.get_uid();
+	assert!(executor.run_until_idle() == 1);
+	assert!(future.wait() == Ok(uid + 100));
+}
+
+#[test]
+fn test_task_pool_cancel() {
+	let counter = Arc::new(AtomicUsize::new(0));
+	let mut pool = TaskPool::new(10);
+	let mut uids = Vec::new();
+	for priority in 1..5 {
+		let c = counter.clone();
+		uids.push(pool.push(TaskType::Sync, priority, Box::new(move || { c.fetch_add(1, Ordering::SeqCst); }), Atom::from("test sync task")).unwrap());
+		let c = counter.clone();
+		uids.push(pool.push(TaskType::Async, priority, Box::new(move || { c.fetch_add(1, Ordering::SeqCst); }), Atom::from("test async task")).unwrap());
+	}
+	assert!(pool.size() == 8);
+
+	//取消的任务不会被执行，且只能取消一次
+	assert!(pool.cancel(uids[3]).is_some());
+	assert!(pool.cancel(uids[3]).is_none());
+	assert!(pool.cancel(uids[0]).is_some());
+	assert!(pool.cancel(0).is_none());
+	assert!(pool.size() == 6);
+
+	let mut task = Task::new();
+	while pool.size() > 0 {
+		pool.pop_untracked(&mut task);
+		task.run();
+		task.reset();
+	}
+	assert!(counter.load(Ordering::SeqCst) == 6);
+
+	//已弹出的任务无法取消
+	for uid in uids {
+		assert!(pool.cancel(uid).is_none());
+	}
+}
+
+#[test]
+fn test_scheduling_policy() {
+	//严格优先级，同优先级同步任务优先，最后弹出延迟任务
+	let mut pool = TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()));
+	for &(task_type, priority) in [(TaskType::Sync, 1), (TaskType::Async, 0), (TaskType::Async, 5), (TaskType::Sync, 3), (TaskType::Async, 3)].iter() {
+		pool.push(task_type, priority, Box::new(|| {}), Atom::from(format!("{:?} {}", task_type, priority))).unwrap();
+	}
+	let mut task = Task::new();
+	let mut order = Vec::new();
+	while pool.size() > 0 {
+		pool.pop_untracked(&mut task);
+		order.push(task.get_info().to_string());
+		task.reset();
+	}
+	assert!(order == vec!["Async 5", "Sync 3", "Async 3", "Sync 1", "Async 0"]);
+
+	//严格优先级，同优先级异步任务先进先出，异步任务队列整理后不变
+	let mut pool = TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()));
+	for i in 0..200 {
+		pool.push(TaskType::Async, 1 + i % 2, Box::new(|| {}), Atom::from(i.to_string())).unwrap();
+	}
+	for uid in (1..200).filter(|uid| uid % 3 == 0) {
+		pool.cancel(uid).unwrap();
+	}
+	let mut order = Vec::new();
+	while pool.size() > 0 {
+		pool.pop(&mut task);
+		pool.complete(&mut task);
+		order.push(task.get_info().parse::<u64>().unwrap());
+		task.reset();
+	}
+	let expect: Vec<u64> = (0..200).filter(|i| i % 2 == 1).chain((0..200).filter(|i| i % 2 == 0)).filter(|i| (i + 1) % 3 != 0).collect();
+	assert!(order == expect);
+
+	//最早截止时间优先，优先级越高截止时间越近
+	let mut pool = TaskPool::with_policy(10, Box::new(EarliestDeadlinePolicy::new(1000000)));
+	for &(task_type, priority) in [(TaskType::Async, 1), (TaskType::Sync, 2), (TaskType::Async, 10), (TaskType::Async, 5), (TaskType::Sync, 20)].iter() {
+		pool.push(task_type, priority, Box::new(|| {}), Atom::from(format!("{:?} {}", task_type, priority))).unwrap();
+	}
+	let mut order = Vec::new();
+	while pool.size() > 0 {
+		pool.pop(&mut task);
+		pool.complete(&mut task);
+		order.push(task.get_info().to_string());
+		task.reset();
+	}
+	assert!(order == vec!["Sync 20", "Async 10", "Async 5", "Sync 2", "Async 1"]);
+
+	//加权轮询，按权重比例交替弹出
+	let mut pool = TaskPool::with_policy(10, Box::new(WeightedRoundRobinPolicy::new()));
+	for _ in 0..4 {
+		pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("sync")).unwrap();
+		pool.push(TaskType::Async, 1, Box::new(|| {}), Atom::from("async")).unwrap();
+	}
+	let mut order = Vec::new();
+	while pool.size() > 0 {
+		pool.pop_untracked(&mut task);
+		order.push(task.get_info().to_string());
+		task.reset();
+	}
+	assert!(order.iter().filter(|info| *info == "sync").count() == 4);
+	assert!(order.windows(3).all(|w| !(w[0] == w[1] && w[1] == w[2])));
+}
+
+#[test]
+fn test_weight_tree() {
+	let mut seed: u64 = 7;
+	let mut random = move |max: u64| {
+		seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+		(seed >> 33) % max
+	};
+
+	let mut tree = WeightTree::new();
+	let mut weights = Vec::new();
+	for _ in 0..1000 {
+		let w = random(10);
+		weights.push(w);
+		tree.push(w);
+	}
+	for _ in 0..1000 {
+		let index = random(1000) as usize;
+		let w = random(10);
+		weights[index] = w;
+		tree.set(index, w);
+	}
+	let total: u64 = weights.iter().sum();
+	assert!(tree.total() == total);
+	for _ in 0..1000 {
+		//线性查找权重落在的位置
+		let weight = random(total);
+		let mut w = weight;
+		let mut index = 0;
+		while w >= weights[index] {
+			w -= weights[index];
+			index += 1;
+		}
+		assert!(tree.find(weight) == Some(index));
+	}
+	assert!(tree.find(total).is_none());
+
+	tree.rebuild(weights.clone());
+	assert!(tree.total() == total && tree.len() == weights.len());
+
+	//大量任务的弹出
+	let mut pool = TaskPool::new(10);
+	for i in 0..10000 {
+		pool.push(TaskType::Async, 1 + i % 7, Box::new(|| {}), Atom::from("async")).unwrap();
+		pool.push(TaskType::Sync, 1 + i % 13, Box::new(|| {}), Atom::from("sync")).unwrap();
+	}
+	let mut task = Task::new();
+	let mut count = 0;
+	while pool.size() > 0 {
+		pool.pop_untracked(&mut task);
+		assert!(task.get_uid() > 0);
+		task.reset();
+		count += 1;
+	}
+	assert!(count == 20000);
+}
+
+#[test]
+fn test_serial_task() {
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let worker_pool = WorkerPool::new(4, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+
+	let counter = Arc::new(AtomicUsize::new(0));
+	let orders = Arc::new(Mutex::new(vec![Vec::new(), Vec::new()]));
+	let runnings = Arc::new(vec![AtomicBool::new(false), AtomicBool::new(false)]);
+	{
+		let &(ref lock, ref cvar) = &*sync;
+		let mut task_pool = lock.lock().unwrap();
+		for i in 0..100 {
+			for key in 0..2 {
+				let counter = counter.clone();
+				let orders = orders.clone();
+				let runnings = runnings.clone();
+				let func = Box::new(move || {
+					//同一个键的任务不会同时执行
+					assert!(!runnings[key].swap(true, Ordering::SeqCst));
+					thread::sleep(Duration::from_micros(100));
+					orders.lock().unwrap()[key].push(i);
+					runnings[key].store(false, Ordering::SeqCst);
+					counter.fetch_add(1, Ordering::SeqCst);
+				});
+				task_pool.push_serial(Atom::from(key.to_string()), TaskType::Async, 10, func, Atom::from("serial task")).unwrap();
+			}
+		}
+		cvar.notify_all();
+	}
+
+	let mut n = 0;
+	while counter.load(Ordering::SeqCst) < 200 && n < 500 {
+		thread::sleep(Duration::from_millis(10));
+		n += 1;
+	}
+	assert!(counter.load(Ordering::SeqCst) == 200);
+	for order in orders.lock().unwrap().iter() {
+		assert!(*order == (0..100).collect::<Vec<usize>>());
+	}
+	assert!(sync.0.lock().unwrap().serial_size() == 0);
+
+	//清空任务池后，同一个键的任务仍然等待正在执行的串行任务完成
+	let mut pool = TaskPool::new(10);
+	let key = Atom::from("running key");
+	let mut running = Task::new();
+	pool.push_serial(key.clone(), TaskType::Async, 10, Box::new(|| {}), Atom::from("first")).unwrap();
+	assert!(pool.pop(&mut running));
+	pool.push_serial(key.clone(), TaskType::Async, 10, Box::new(|| {}), Atom::from("second")).unwrap();
+	assert!(pool.clear().len() == 1);
+	pool.push_serial(key.clone(), TaskType::Async, 10, Box::new(|| {}), Atom::from("third")).unwrap();
+	let mut task = Task::new();
+	assert!(!pool.pop(&mut task));
+	assert!(pool.complete(&mut running));
+	assert!(pool.pop(&mut task) && task.get_info() == "third");
+	pool.complete(&mut task);
+	assert!(pool.serial_size() == 0 && pool.is_idle());
+}
+
+#[test]
+fn test_bounded_task_pool() {
+	//拒绝
+	let mut pool = TaskPool::new(10);
+	pool.set_capacity(3);
+	pool.set_type_capacity(TaskType::Async, 1);
+	assert!(pool.push(TaskType::Async, 1, Box::new(|| {}), Atom::from("async")).is_ok());
+	assert!(pool.push(TaskType::Async, 1, Box::new(|| {}), Atom::from("async")).is_err());
+	assert!(pool.push(TaskType::Sync, 0, Box::new(|| {}), Atom::from("delay sync")).is_ok());
+	assert!(pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("sync")).is_ok());
+	assert!(pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("sync")).unwrap_err().kind() == PushErrorKind::Full);
+	assert!(pool.full_statistics().rejected == 2);
+
+	//驱逐延迟任务，延迟任务不能驱逐延迟任务
+	pool.set_full_policy(FullPolicy::EvictDelay);
+	assert!(pool.push(TaskType::Sync, 0, Box::new(|| {}), Atom::from("delay sync")).is_err());
+	assert!(pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("sync")).is_ok());
+	assert!(pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("sync")).is_err());
+	assert!(pool.size() == 3 && pool.full_statistics().evicted == 1);
+
+	//阻塞超时
+	pool.set_full_policy(FullPolicy::Block(Duration::from_millis(50)));
+	let sync = Arc::new((Mutex::new(pool), Condvar::new()));
+	assert!(push_task(&sync, TaskType::Sync, |pool| pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("sync"))).unwrap_err().kind() == PushErrorKind::Timeout);
+	assert!(sync.0.lock().unwrap().full_statistics().timeout == 1);
+
+	//阻塞直到任务被弹出
+	sync.0.lock().unwrap().set_full_policy(FullPolicy::Block(Duration::from_millis(5000)));
+	let copy = sync.clone();
+	let pushed = thread::spawn(move || {
+		push_task(&copy, TaskType::Sync, |pool| pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("sync"))).is_ok()
+	});
+	while sync.0.lock().unwrap().blocked_size() == 0 {
+		thread::sleep(Duration::from_millis(1));
+	}
+	{
+		let &(ref lock, ref cvar) = &*sync;
+		let mut task = Task::new();
+		lock.lock().unwrap().pop_untracked(&mut task);
+		cvar.notify_all();
+	}
+	assert!(pushed.join().unwrap());
+	assert!(sync.0.lock().unwrap().full_statistics().blocked == 2);
+}
+
+//拒绝所有任务的执行器
+fn reject_task(_task_type: TaskType, _priority: u64, _func: Box<FnBox() + Send>, info: Atom) -> NormalResult<usize, String> {
+	Err(format!("task rejected, info: {}", *info))
+}
+
+#[test]
+fn test_future_pool_reject() {
+	//执行器拒绝任务时分派失败，不会返回永远不会完成的未来任务
+	let pool = FutTaskPool::new(reject_task);
+	assert!(pool.spawn::<(), ()>(Box::new(|_, _, _, _| {}), 1000).is_err());
+}
+
+#[test]
+fn test_timed_task() {
+	//使用手动时钟，定时任务只在推进时钟后到达执行时间
+	let mut pool = TaskPool::new(10);
+	pool.set_manual_clock(true);
+	let uid0 = pool.push_after(TaskType::Async, 10, Box::new(|| {}), Atom::from("timed task0"), Duration::from_millis(30)).unwrap();
+	let uid1 = pool.push_after(TaskType::Sync, 10, Box::new(|| {}), Atom::from("timed task1"), Duration::from_millis(30)).unwrap();
+	assert!(pool.poll_timed() == 0 && pool.size() == 0 && pool.timed_size() == 2);
+	assert!(pool.next_deadline() == Some(pool.now() + Duration::from_millis(30)));
+
+	//未到执行时间的定时任务可以取消
+	assert!(pool.cancel(uid1).is_some());
+	pool.advance_clock(Duration::from_millis(29));
+	assert!(pool.poll_timed() == 0 && pool.timed_size() == 1);
+	pool.advance_clock(Duration::from_millis(1));
+	assert!(pool.poll_timed() == 1 && pool.size() == 1 && pool.timed_size() == 0);
+
+	let mut task = Task::new();
+	pool.pop_untracked(&mut task);
+	assert!(task.get_uid() == uid0);
+
+	//大量取消或移除定时任务后，剩余的定时任务仍按执行时间加入任务队列
+	let mut uids = Vec::new();
+	for i in 0..200 {
+		uids.push(pool.push_after(TaskType::Sync, 10, Box::new(|| {}), Atom::from(if i % 2 == 0 { "removed task" } else { "timed task" }), Duration::from_millis(30)).unwrap());
+	}
+	for uid in uids.iter().skip(1).step_by(4) {
+		assert!(pool.cancel(*uid).is_some());
+	}
+	assert!(pool.remove_by(|t| &*t.get_info() == "removed task").len() == 100 && pool.timed_size() == 50);
+	pool.advance_clock(Duration::from_millis(30));
+	assert!(pool.poll_timed() == 50 && pool.size() == 50 && pool.timed_size() == 0);
+
+	//关闭手动时钟后，任务池时间不会倒退
+	let now = pool.now();
+	pool.set_manual_clock(false);
+	assert!(!pool.is_manual_clock() && pool.now() >= now);
+}
+
+#[test]
+fn test_task_aging() {
+	//延迟任务老化后以老化后的优先级加入任务队列，严格优先级调度时先于低优先级任务弹出
+	let mut pool = TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()));
+	pool.set_manual_clock(true);
+	pool.set_aging(Some(AgingConfig::new(Duration::from_millis(10), 1, 5)));
+	for _ in 0..3 {
+		pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("low task")).unwrap();
+	}
+	let delay = pool.push(TaskType::Sync, 0, Box::new(|| {}), Atom::from("delay task")).unwrap();
+	pool.advance_clock(Duration::from_millis(35));
+	let mut task = Task::new();
+	pool.pop_untracked(&mut task);
+	assert!(task.get_uid() == delay && task.get_priority() == 0 && task.get_boost() == 3);
+	assert!(task.get_aged_priority() == 3 && pool.size() == 3);
+
+	//超过最大等待时间的任务按等待时间从长到短优先弹出
+	let mut pool = TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()));
+	pool.set_manual_clock(true);
+	pool.set_aging(Some(AgingConfig::new(Duration::from_millis(10), 0, 0).with_max_wait(Duration::from_millis(30))));
+	let delay = pool.push(TaskType::Async, 0, Box::new(|| {}), Atom::from("delay task")).unwrap();
+	pool.advance_clock(Duration::from_millis(1));
+	let low = pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("low task")).unwrap();
+	pool.advance_clock(Duration::from_millis(28));
+	pool.push(TaskType::Async, 10, Box::new(|| {}), Atom::from("high task")).unwrap();
+	pool.pop_untracked(&mut task);
+	assert!(task.get_priority() == 10); //还没有任务超过最大等待时间
+	pool.advance_clock(Duration::from_millis(11));
+	for _ in 0..5 {
+		pool.push(TaskType::Async, 10, Box::new(|| {}), Atom::from("high task")).unwrap();
+	}
+	pool.pop_untracked(&mut task);
+	assert!(task.get_uid() == delay);
+	pool.pop_untracked(&mut task);
+	assert!(task.get_uid() == low);
+	pool.pop_untracked(&mut task);
+	assert!(task.get_priority() == 10 && pool.size() == 4);
+
+	//加入队列头的任务不会遮挡队列中超过最大等待时间的任务
+	let mut pool = TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()));
+	pool.set_manual_clock(true);
+	pool.set_aging(Some(AgingConfig::new(Duration::from_millis(10), 0, 0).with_max_wait(Duration::from_millis(30))));
+	let old = pool.push(TaskType::Sync, 1, Box::new(|| {}), Atom::from("old task")).unwrap();
+	pool.advance_clock(Duration::from_millis(30));
+	let imme = pool.push(TaskType::SyncImme, 1, Box::new(|| {}), Atom::from("imme task")).unwrap();
+	pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("high task")).unwrap();
+	pool.pop_untracked(&mut task);
+	assert!(task.get_uid() == old);
+	pool.pop_untracked(&mut task);
+	assert!(task.get_priority() == 10);
+	pool.pop_untracked(&mut task);
+	assert!(task.get_uid() == imme && pool.size() == 0);
+
+	//异步任务按优先级分桶老化，桶为空时清除老化
+	let weight = Arc::new(AtomicUsize::new(0));
+	let mut pool = TaskPool::with_policy(10, Box::new(FrontPolicy(weight.clone())));
+	pool.set_manual_clock(true);
+	pool.set_aging(Some(AgingConfig::new(Duration::from_millis(20), 1, 5)));
+	for _ in 0..3 {
+		pool.push(TaskType::Async, 1, Box::new(|| {}), Atom::from("low task")).unwrap();
+	}
+	pool.push(TaskType::Async, 8, Box::new(|| {}), Atom::from("high task")).unwrap();
+	pool.advance_clock(Duration::from_millis(70));
+	assert!(pool.pop_untracked(&mut task) && task.get_priority() == 1);
+	assert!(weight.load(Ordering::SeqCst) == 4 * 3 + 8);
+	pool.pop_untracked(&mut task);
+	pool.pop_untracked(&mut task);
+	assert!(pool.pop_untracked(&mut task) && task.get_priority() == 8 && weight.load(Ordering::SeqCst) == 8);
+	pool.push(TaskType::Async, 1, Box::new(|| {}), Atom::from("low task")).unwrap();
+	assert!(pool.pop_untracked(&mut task) && weight.load(Ordering::SeqCst) == 1);
+}
+
+//记录异步任务池的权重，并弹出最先加入的异步任务
+struct FrontPolicy(Arc<AtomicUsize>);
+
+impl SchedulingPolicy for FrontPolicy {
+	fn pop(&mut self, _sync_pool: &mut SyncPool<Box<FnBox() + Send>>, async_pool: &mut AsyncPool<Box<FnBox() + Send>>, _rng: &mut PolicyRng) -> Option<Task> {
+		self.0.store(async_pool.weight() as usize, Ordering::SeqCst);
+		async_pool.pop_front()
+	}
+}
+
+#[test]
+fn test_rate_limit() {
+	//受限任务类别的任务没有令牌时继续等待，不阻塞其它任务类别的任务
+	let mut pool = TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()));
+	pool.set_manual_clock(true);
+	assert!(pool.set_rate_limit(Atom::from("limit task"), 50, 0).is_err() && pool.get_rate_limit(&Atom::from("limit task")).is_none());
+	pool.set_rate_limit(Atom::from("limit task"), 50, 1).unwrap();
+	assert!(pool.get_rate_limit(&Atom::from("limit task")) == Some((50, 1)));
+	let first = pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("limit task")).unwrap();
+	let second = pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("limit task")).unwrap();
+	let other = pool.push(TaskType::Async, 1, Box::new(|| {}), Atom::from("other task")).unwrap();
+	let mut task = Task::new();
+	assert!(pool.pop_untracked(&mut task) && task.get_uid() == first);
+	assert!(pool.pop_untracked(&mut task) && task.get_uid() == other);
+	assert!(!pool.pop_untracked(&mut task) && pool.throttled_size() == 1 && pool.size() == 1);
+	assert!(pool.next_deadline() == Some(pool.now() + Duration::from_millis(20))); //工作者等待到等待的任务获得令牌的时间
+
+	//生成令牌后弹出等待的任务
+	pool.advance_clock(Duration::from_millis(19));
+	assert!(!pool.pop_untracked(&mut task));
+	pool.advance_clock(Duration::from_millis(1));
+	assert!(pool.pop_untracked(&mut task) && task.get_uid() == second);
+	assert!(pool.throttled_size() == 0 && pool.size() == 0);
+
+	//移除限制后，等待的任务立即弹出
+	pool.set_rate_limit(Atom::from("slow task"), 1, 1).unwrap();
+	pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("slow task")).unwrap();
+	let throttled = pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("slow task")).unwrap();
+	assert!(pool.pop_untracked(&mut task));
+	assert!(!pool.pop_untracked(&mut task) && pool.throttled_size() == 1);
+	pool.remove_rate_limit(&Atom::from("slow task"));
+	assert!(pool.get_rate_limit(&Atom::from("slow task")).is_none());
+	assert!(pool.pop_untracked(&mut task) && task.get_uid() == throttled);
+
+	//等待的任务可以被取消
+	pool.set_rate_limit(Atom::from("slow task"), 1, 1).unwrap();
+	pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("slow task")).unwrap();
+	let cancelled = pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("slow task")).unwrap();
+	assert!(pool.pop_untracked(&mut task));
+	assert!(!pool.pop_untracked(&mut task));
+	assert!(pool.cancel(cancelled).is_some() && pool.throttled_size() == 0);
+
+	//多个任务类别都有等待的任务时，按轮询顺序释放获得令牌的任务
+	let mut pool = TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()));
+	let classes = ["class a", "class b", "class c"];
+	for class in classes.iter() {
+		pool.set_rate_limit(Atom::from(*class), 0, 1).unwrap();
+		pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from(*class)).unwrap();
+	}
+	for _ in 0..2 {
+		for class in classes.iter() {
+			pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from(*class)).unwrap();
+		}
+	}
+	for _ in 0..3 {
+		assert!(pool.pop_untracked(&mut task));
+	}
+	assert!(!pool.pop_untracked(&mut task) && pool.throttled_size() == 6);
+	for class in classes.iter() {
+		pool.remove_rate_limit(&Atom::from(*class));
+	}
+	let mut order = Vec::new();
+	while pool.pop_untracked(&mut task) {
+		order.push(task.get_info().to_string());
+	}
+	assert!(order == vec!["class a", "class b", "class c", "class a", "class b", "class c"]);
+}
+
+#[test]
+fn test_steal_pool() {
+	let pool = Arc::new(StealPool::new());
+	let worker_pool = WorkerPool::new(4, 1024 * 1024, 10000);
+	worker_pool.run(pool.clone());
+
+	//外部线程加入的任务进入全局任务队列，工作者加入的任务进入本地任务队列，并可被其它工作者窃取
+	let counter = Arc::new(AtomicUsize::new(0));
+	for _ in 0..1000 {
+		let p = pool.clone();
+		let counter = counter.clone();
+		let func = Box::new(move || {
+			let c = counter.clone();
+			p.push(TaskType::Async, 10, Box::new(move || {
+				c.fetch_add(1, Ordering::SeqCst);
+			}), Atom::from("local task")).unwrap();
+			counter.fetch_add(1, Ordering::SeqCst);
 		});
+		pool.push(TaskType::Async, 10, func, Atom::from("steal task")).unwrap();
+	}
+	let c = counter.clone();
+	pool.push(TaskType::Sync, 0, Box::new(move || {
+		c.fetch_add(1, Ordering::SeqCst);
+	}), Atom::from("delay task")).unwrap();
 
-		thread::spawn(move || {
-			let read = move |f10: Arc<AsyncFile>, result: Result<Vec<u8>>| {
-				assert!(result.is_ok() && result.ok().unwrap().len() == 250);
-				let write = move |_f11: Arc<AsyncFile>, result: Result<usize>| {
-					assert!(result.is_ok());
-				};
-				f10.pwrite(WriteOptions::SyncAll(true), 250, Vec::from("\nHello Rust0\n".as_bytes()), Box::new(write));
-			};
-			f1.pread(0, 250, Box::new(read));
+	let mut n = 0;
+	while counter.load(Ordering::SeqCst) < 2001 && n < 500 {
+		thread::sleep(Duration::from_millis(10));
+		n += 1;
+	}
+	assert!(counter.load(Ordering::SeqCst) == 2001 && pool.size() == 0);
+	for uid in worker_pool.workers(WorkerStatus::Running as usize) {
+		assert!(worker_pool.stop(uid));
+	}
+}
+
+#[test]
+fn test_task_group() {
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let worker_pool = WorkerPool::new(4, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+
+	//统计成员的完成情况，包括执行时发生异常的成员，关闭且所有成员结束后调用完成回调
+	let group = TaskGroup::new();
+	let result: Arc<Mutex<Option<GroupStatus>>> = Arc::new(Mutex::new(None));
+	let r = result.clone();
+	group.set_callback(Box::new(move |status: GroupStatus| {
+		*r.lock().unwrap() = Some(status);
+	}));
+	for i in 0..10 {
+		group.push(&sync, TaskType::Async, 10, Box::new(move || {
+			if i == 5 {
+				panic!("task group member panic");
+			}
+			thread::sleep(Duration::from_millis(1));
+		}), Atom::from("group task")).unwrap();
+	}
+	group.close();
+	let status = group.join_timeout(Duration::from_millis(5000)).unwrap();
+	assert!(status.total == 10 && status.completed == 9 && status.panicked == 1 && status.cancelled == 0);
+	let mut n = 0;
+	while result.lock().unwrap().is_none() && n < 500 {
+		thread::sleep(Duration::from_millis(10));
+		n += 1;
+	}
+	let status = result.lock().unwrap().clone().unwrap();
+	assert!(status.is_done() && status.finished() == 10);
+	assert!(group.push(&sync, TaskType::Async, 10, Box::new(|| {}), Atom::from("group task")).is_err());
+
+	//取消所有等待中的成员
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let group = TaskGroup::new();
+	for _ in 0..5 {
+		group.push(&sync, TaskType::Sync, 10, Box::new(|| {}), Atom::from("group task")).unwrap();
+	}
+	assert!(!group.is_done() && group.cancel() == 5 && group.is_done() && group.is_cancel());
+	let status = group.status();
+	assert!(status.total == 5 && status.cancelled == 5 && sync.0.lock().unwrap().size() == 0);
+	assert!(group.push(&sync, TaskType::Sync, 10, Box::new(|| {}), Atom::from("group task")).is_err());
+
+	//加入失败的成员不计入任务组
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	sync.0.lock().unwrap().set_capacity(1);
+	let group = TaskGroup::new();
+	group.push(&sync, TaskType::Sync, 10, Box::new(|| {}), Atom::from("group task")).unwrap();
+	assert!(group.push(&sync, TaskType::Sync, 10, Box::new(|| {}), Atom::from("group task")).is_err());
+	let status = group.status();
+	assert!(status.total == 1 && status.cancelled == 0);
+}
+
+#[test]
+fn test_task_graph() {
+	let ext = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let store = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let ext_workers = WorkerPool::new(2, 1024 * 1024, 10000);
+	let store_workers = WorkerPool::new(2, 1024 * 1024, 10000);
+	ext_workers.run(ext.clone());
+	store_workers.run(store.clone());
+
+	//节点在所有前置节点都成功后才加入自己的任务池，节点失败时跳过所有后续节点
+	let orders = Arc::new(Mutex::new(Vec::new()));
+	let node = |name: &'static str, is_ok: bool| -> Box<FnBox() -> NormalResult<(), String> + Send> {
+		let orders = orders.clone();
+		Box::new(move || {
+			thread::sleep(Duration::from_millis(5));
+			orders.lock().unwrap().push(name);
+			if is_ok {
+				Ok(())
+			} else {
+				Err(format!("{} failed", name))
+			}
+		})
+	};
+	let mut graph = TaskGraph::new();
+	let decompress = graph.add_node(Atom::from("decompress"), &ext, TaskType::Async, 10, node("decompress", true), &[]).unwrap();
+	let parse = graph.add_node(Atom::from("parse"), &ext, TaskType::Async, 10, node("parse", true), &[decompress]).unwrap();
+	let check = graph.add_node(Atom::from("check"), &ext, TaskType::Async, 10, node("check", false), &[decompress]).unwrap();
+	let index = graph.add_node(Atom::from("index"), &store, TaskType::Sync, 10, node("index", true), &[parse]).unwrap();
+	let report = graph.add_node(Atom::from("report"), &store, TaskType::Sync, 10, node("report", true), &[parse, check]).unwrap();
+	let upload = graph.add_node(Atom::from("upload"), &store, TaskType::Sync, 10, node("upload", true), &[report]).unwrap();
+	assert!(graph.add_node(Atom::from("invalid"), &store, TaskType::Sync, 10, node("invalid", true), &[10]).is_err());
+	let finished = Arc::new(AtomicBool::new(false));
+	let f = finished.clone();
+	graph.set_callback(Box::new(move |statuses: Vec<(Atom, NodeStatus)>| {
+		assert!(statuses.len() == 6);
+		f.store(true, Ordering::SeqCst);
+	}));
+
+	let handle = graph.run();
+	let statuses = handle.join_timeout(Duration::from_millis(5000)).unwrap();
+	assert!(statuses[decompress].1 == NodeStatus::Completed && statuses[parse].1 == NodeStatus::Completed);
+	assert!(statuses[index].1 == NodeStatus::Completed);
+	assert!(statuses[check].1 == NodeStatus::Failed("check failed".to_string()));
+	assert!(statuses[report].1 == NodeStatus::Skipped(vec![check, report]));
+	assert!(statuses[upload].1 == NodeStatus::Skipped(vec![check, report, upload]));
+	let orders = orders.lock().unwrap().clone();
+	assert!(orders.len() == 4 && orders[0] == "decompress");
+	assert!(orders.iter().position(|n| *n == "parse") < orders.iter().position(|n| *n == "index"));
+	let mut n = 0;
+	while !finished.load(Ordering::SeqCst) && n < 500 {
+		thread::sleep(Duration::from_millis(10));
+		n += 1;
+	}
+	assert!(finished.load(Ordering::SeqCst));
+
+	//加入任务池失败的节点失败
+	let full = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	full.0.lock().unwrap().set_capacity(1);
+	full.0.lock().unwrap().push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("blocked task")).unwrap();
+	let mut graph = TaskGraph::new();
+	let a = graph.add_node(Atom::from("a"), &full, TaskType::Sync, 10, Box::new(|| Ok(())), &[]).unwrap();
+	let b = graph.add_node(Atom::from("b"), &full, TaskType::Sync, 10, Box::new(|| Ok(())), &[a]).unwrap();
+	let handle = graph.run();
+	assert!(handle.is_done());
+	match handle.status(a) {
+		Some(NodeStatus::Failed(e)) => assert!(e.starts_with("task pool full")),
+		_ => assert!(false),
+	}
+	assert!(handle.status(b) == Some(NodeStatus::Skipped(vec![a, b])));
+
+	//节点任务未执行就被清空时节点失败，任务函数在任务池锁外释放，所以完成回调可以访问任务池
+	let cleared = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let mut graph = TaskGraph::new();
+	let a = graph.add_node(Atom::from("a"), &cleared, TaskType::Sync, 10, Box::new(|| Ok(())), &[]).unwrap();
+	let b = graph.add_node(Atom::from("b"), &cleared, TaskType::Sync, 10, Box::new(|| Ok(())), &[a]).unwrap();
+	let pool = cleared.clone();
+	graph.set_callback(Box::new(move |_statuses: Vec<(Atom, NodeStatus)>| {
+		assert!(pool.0.lock().unwrap().size() == 0);
+	}));
+	let handle = graph.run();
+	let funcs = cleared.0.lock().unwrap().clear();
+	assert!(funcs.len() == 1 && !handle.is_done());
+	drop(funcs);
+	assert!(handle.is_done() && handle.status(b) == Some(NodeStatus::Skipped(vec![a, b])));
+	match handle.status(a) {
+		Some(NodeStatus::Failed(e)) => assert!(e == "graph node dropped without running"),
+		_ => assert!(false),
+	}
+}
+
+#[test]
+fn test_task_handle() {
+	//任务未执行时可以取消，任务结果为错误
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let handle = spawn(&sync, TaskType::Async, 10, || 1, Atom::from("typed task")).unwrap();
+	assert!(handle.try_get().is_none() && handle.wait_timeout(Duration::from_millis(10)).is_none());
+	assert!(sync.0.lock().unwrap().cancel(handle.get_uid()).is_some());
+	assert!(handle.is_ready() && handle.wait().is_err());
+
+	let worker_pool = WorkerPool::new(2, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+
+	//获取任务函数的返回值
+	let handle = spawn(&sync, TaskType::Async, 10, || {
+		thread::sleep(Duration::from_millis(10));
+		"typed result".to_string()
+	}, Atom::from("typed task")).unwrap();
+	assert!(handle.wait_timeout(Duration::from_millis(5000)) == Some(Ok("typed result".to_string())));
+	assert!(handle.try_get().unwrap().is_err());
+
+	//任务发生异常时任务结果为错误
+	let handle = spawn(&sync, TaskType::Sync, 10, || -> usize {
+		panic!("typed task panic");
+	}, Atom::from("typed task")).unwrap();
+	match handle.wait() {
+		Err(e) => assert!(e.contains("typed task panic")),
+		Ok(_) => assert!(false),
+	}
+}
+
+#[test]
+fn test_drain() {
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let counter = Arc::new(AtomicUsize::new(0));
+	for priority in 0..10 {
+		let c = counter.clone();
+		push_task(&sync, TaskType::Async, move |pool| {
+			pool.push(TaskType::Async, priority, Box::new(move || {
+				thread::sleep(Duration::from_millis(5));
+				c.fetch_add(1, Ordering::SeqCst);
+			}), Atom::from("drain task"))
+		}).unwrap();
+	}
+
+	//排空模式下移除低优先级任务，并拒绝加入任务
+	let funcs = sync.0.lock().unwrap().drain(5);
+	assert!(funcs.len() == 5);
+	let ran = Arc::new(AtomicBool::new(false));
+	let ran_copy = ran.clone();
+	let e = push_task(&sync, TaskType::Sync, |pool| {
+		pool.push(TaskType::Sync, 10, Box::new(move || ran_copy.store(true, Ordering::SeqCst)), Atom::from("drain task"))
+	}).unwrap_err();
+	assert!(e.kind() == PushErrorKind::Draining);
+	e.into_payload()(); //拒绝加入的任务交还给调用者
+	assert!(ran.load(Ordering::SeqCst));
+	assert!(!wait_idle(&sync, Duration::from_millis(10)));
+
+	//已加入的任务执行完成后任务池空闲
+	let worker_pool = WorkerPool::new(2, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+	assert!(counter.load(Ordering::SeqCst) == 5);
+	assert!(sync.0.lock().unwrap().is_drain() && sync.0.lock().unwrap().running_size() == 0);
+
+	//退出排空模式后允许加入任务
+	sync.0.lock().unwrap().resume();
+	assert!(push_task(&sync, TaskType::Sync, |pool| {
+		pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("drain task"))
+	}).is_ok());
+}
+
+struct EventRecorder(Mutex<Vec<(String, usize, Option<u32>)>>);
+
+impl TaskObserver for EventRecorder {
+	fn on_push(&self, event: &TaskEvent) {
+		self.0.lock().unwrap().push(("push".to_string(), event.uid, event.worker));
+	}
+
+	fn on_pop(&self, event: &TaskEvent) {
+		self.0.lock().unwrap().push(("pop".to_string(), event.uid, event.worker));
+	}
+
+	fn on_start(&self, event: &TaskEvent) {
+		self.0.lock().unwrap().push(("start".to_string(), event.uid, event.worker));
+	}
+
+	fn on_finish(&self, event: &TaskEvent) {
+		self.0.lock().unwrap().push(("finish".to_string(), event.uid, event.worker));
+	}
+
+	fn on_panic(&self, event: &TaskEvent) {
+		self.0.lock().unwrap().push(("panic".to_string(), event.uid, event.worker));
+	}
+
+	fn on_cancel(&self, event: &TaskEvent) {
+		self.0.lock().unwrap().push(("cancel".to_string(), event.uid, event.worker));
+	}
+}
+
+#[test]
+fn test_observer() {
+	let recorder = Arc::new(EventRecorder(Mutex::new(Vec::new())));
+	let mut pool = TaskPool::new(10);
+	pool.set_observer(Some(PoolObserver::new(Atom::from("observed pool"), recorder.clone())));
+	let sync = Arc::new((Mutex::new(pool), Condvar::new()));
+
+	//加入和取消事件
+	let cancelled = push_task(&sync, TaskType::Async, |pool| {
+		pool.push(TaskType::Async, 10, Box::new(|| {}), Atom::from("observed task"))
+	}).unwrap();
+	assert!(sync.0.lock().unwrap().cancel(cancelled).is_some());
+	assert!(*recorder.0.lock().unwrap() == vec![("push".to_string(), cancelled, None), ("cancel".to_string(), cancelled, None)]);
+	recorder.0.lock().unwrap().clear();
+
+	//清空任务池时，每个被丢弃的任务都产生取消事件
+	let cleared = push_task(&sync, TaskType::Sync, |pool| {
+		pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("observed task"))
+	}).unwrap();
+	let funcs = sync.0.lock().unwrap().clear();
+	assert!(funcs.len() == 1);
+	assert!(*recorder.0.lock().unwrap() == vec![("push".to_string(), cleared, None), ("cancel".to_string(), cleared, None)]);
+	recorder.0.lock().unwrap().clear();
+
+	//弹出、开始和结束事件，以及异常事件
+	let worker_pool = WorkerPool::new(1, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+	let finished = push_task(&sync, TaskType::Async, |pool| {
+		pool.push(TaskType::Async, 10, Box::new(|| {}), Atom::from("observed task"))
+	}).unwrap();
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+	let panicked = push_task(&sync, TaskType::Sync, |pool| {
+		pool.push(TaskType::Sync, 10, Box::new(|| panic!("observed task panic")), Atom::from("observed task"))
+	}).unwrap();
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+
+	let events: Vec<(String, usize)> = recorder.0.lock().unwrap().iter().map(|&(ref e, uid, _)| (e.clone(), uid)).collect();
+	assert!(events == vec![
+		("push".to_string(), finished), ("pop".to_string(), finished), ("start".to_string(), finished), ("finish".to_string(), finished),
+		("push".to_string(), panicked), ("pop".to_string(), panicked), ("start".to_string(), panicked), ("panic".to_string(), panicked),
+	]);
+	assert!(recorder.0.lock().unwrap().iter().all(|&(ref e, _, worker)| worker.is_some() == (e == "start" || e == "finish" || e == "panic")));
+}
+
+#[test]
+fn test_wait_histogram() {
+	let mut histogram = Histogram::new();
+	for micros in 1..101 {
+		histogram.record(Duration::from_micros(micros));
+	}
+	assert!(histogram.count() == 100 && histogram.max() == Duration::from_micros(100));
+	assert!(histogram.percentile(0.5) == Duration::from_micros(63));
+	assert!(histogram.percentile(0.99) == Duration::from_micros(100));
+
+	//统计窗口内等待时长的99分位数超过阈值，则下一个窗口丢弃可丢弃的任务
+	let shed = Arc::new(AtomicUsize::new(0));
+	let c = shed.clone();
+	let mut pool = TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()));
+	pool.set_manual_clock(true);
+	pool.set_shedding(Duration::from_millis(1), Duration::from_millis(10), Arc::new(move |_uid, info, wait| {
+		assert!(&*info == "sheddable task" && wait == Duration::from_millis(30));
+		c.fetch_add(1, Ordering::SeqCst);
+	}));
+	let first = pool.push_sheddable(TaskType::Async, 10, Box::new(|| {}), Atom::from("sheddable task")).unwrap();
+	pool.push_sheddable(TaskType::Async, 10, Box::new(|| {}), Atom::from("sheddable task")).unwrap();
+	pool.push_sheddable(TaskType::Async, 10, Box::new(|| {}), Atom::from("sheddable task")).unwrap();
+	let normal = pool.push(TaskType::Async, 1, Box::new(|| {}), Atom::from("normal task")).unwrap();
+	let mut task = Task::new();
+	pool.advance_clock(Duration::from_millis(15));
+	assert!(pool.pop_untracked(&mut task) && task.get_uid() == first && !pool.is_overload());
+	pool.advance_clock(Duration::from_millis(15));
+	assert!(pool.pop_untracked(&mut task) && task.get_uid() == normal && pool.is_overload());
+	assert!(pool.shed_size() == 2 && shed.load(Ordering::SeqCst) == 0 && pool.size() == 0);
+	let released = pool.take_released();
+	assert!(released.len() == 2 && pool.take_released().is_empty());
+	drop(released); //丢弃的任务函数由调用者在任务池锁外释放，并在释放时调用丢弃任务的回调
+	assert!(shed.load(Ordering::SeqCst) == 2);
+
+	//丢弃任务的回调在任务池锁外调用，可以在回调中访问任务池
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let weak = Arc::downgrade(&sync);
+	let sizes = Arc::new(Mutex::new(Vec::new()));
+	let sizes_copy = sizes.clone();
+	sync.0.lock().unwrap().set_shedding(Duration::from_millis(0), Duration::from_millis(0), Arc::new(move |_uid, _info, _wait| {
+		if let Some(sync) = weak.upgrade() {
+			sizes_copy.lock().unwrap().push(sync.0.lock().unwrap().size());
+		}
+	}));
+	let mut executor = TestExecutor::new(sync.clone(), 0).unwrap();
+	for _ in 0..3 {
+		push_task(&sync, TaskType::Async, |pool| pool.push_sheddable(TaskType::Async, 10, Box::new(|| {}), Atom::from("sheddable task"))).unwrap();
+	}
+	executor.advance(Duration::from_millis(2));
+	assert!(executor.step());
+	executor.advance(Duration::from_millis(1));
+	assert!(executor.run_until_idle() == 0 && executor.count() == 1);
+	assert!(sync.0.lock().unwrap().shed_size() == 2 && *sizes.lock().unwrap() == vec![0, 0]);
+
+	//丢弃的任务也记录等待时长
+	assert!(pool.wait_histogram(TaskType::Async).unwrap().count() == 4);
+	assert!(pool.wait_histogram(TaskType::Sync).unwrap().count() == 0);
+	assert!(pool.priority_wait_histogram(10).unwrap().count() == 3);
+	assert!(pool.priority_wait_histogram(10).unwrap().max() == Duration::from_millis(30));
+	pool.clear_wait_histograms();
+	assert!(pool.priority_wait_histogram(10).is_none());
+}
+
+#[test]
+fn test_remove_by_tag() {
+	let mut pool = TaskPool::new(10);
+	let player = Atom::from("player 1");
+	let scene = Atom::from("scene 1");
+	for priority in 0..4 {
+		pool.push_tagged(TaskType::Async, priority, Box::new(|| {}), Atom::from("player async task"), vec![player.clone()]).unwrap();
+		pool.push_tagged(TaskType::Sync, priority, Box::new(|| {}), Atom::from("player sync task"), vec![player.clone(), scene.clone()]).unwrap();
+		pool.push_tagged(TaskType::Async, priority, Box::new(|| {}), Atom::from("scene task"), vec![scene.clone()]).unwrap();
+		pool.push(TaskType::Sync, priority, Box::new(|| {}), Atom::from("other task")).unwrap();
+	}
+	pool.push_after(TaskType::Sync, 10, Box::new(|| {}), Atom::from("timed task"), Duration::from_millis(1000)).unwrap();
+	assert!(pool.size() == 16);
+
+	//移除所有队列中带有指定标签的任务
+	let infos = pool.remove_by_tag(&player);
+	assert!(infos.len() == 8 && pool.size() == 8);
+	assert!(infos.iter().all(|&(ref info, _)| &**info == "player async task" || &**info == "player sync task"));
+	assert!(pool.remove_by_tag(&player).is_empty());
+
+	//按条件移除任务
+	let infos = pool.remove_by(|t| t.get_priority() == 0 || &*t.get_info() == "timed task");
+	assert!(infos.len() == 3 && pool.size() == 6 && pool.timed_size() == 0);
+
+	//移除后权重保持一致，剩余任务都可以弹出
+	let mut task = Task::new();
+	let mut count = 0;
+	while pool.pop_untracked(&mut task) {
+		assert!(!task.has_tag(&player) && task.get_priority() > 0);
+		count += 1;
+	}
+	assert!(count == 6 && pool.size() == 0);
+
+	//被移除的任务函数由调用者释放，以便在任务池锁外释放
+	let dropped = Arc::new(AtomicUsize::new(0));
+	let counter = DropCounter(dropped.clone());
+	pool.push_tagged(TaskType::Sync, 1, Box::new(move || { let _counter = &counter; }), Atom::from("player sync task"), vec![player.clone()]).unwrap();
+	pool.push_tagged(TaskType::Sync, 1, Box::new(|| {}), Atom::from("player sync task"), vec![player.clone()]).unwrap();
+	let removed = pool.remove_by_tag(&player);
+	assert!(removed.len() == 2 && pool.size() == 0 && dropped.load(Ordering::SeqCst) == 0);
+	drop(removed);
+	assert!(dropped.load(Ordering::SeqCst) == 1);
+}
+
+#[test]
+fn test_message_pool() {
+	//任务池的负载可以是任意类型的数据，由消费者弹出任务并取出负载
+	let mut pool: TaskPool<Vec<u8>> = TaskPool::with_payload(10, Box::new(StrictPriorityPolicy::new()));
+	pool.push(TaskType::Async, 1, vec![1], Atom::from("low packet")).unwrap();
+	pool.push(TaskType::Sync, 10, vec![2, 2], Atom::from("high packet")).unwrap();
+	pool.push(TaskType::Async, 0, vec![3, 3, 3], Atom::from("delay packet")).unwrap();
+	let cancelled = pool.push(TaskType::Async, 5, vec![4], Atom::from("cancelled packet")).unwrap();
+	assert!(pool.cancel(cancelled) == Some(vec![4]));
+
+	let mut task = Task::new();
+	let mut packets = Vec::new();
+	while pool.pop(&mut task) {
+		packets.push(task.take_payload().unwrap());
+		pool.complete(&mut task);
+	}
+	assert!(packets == vec![vec![2, 2], vec![1], vec![3, 3, 3]]);
+	assert!(pool.is_idle());
+}
+
+//释放时增加计数
+struct DropCounter(Arc<AtomicUsize>);
+
+impl Drop for DropCounter {
+	fn drop(&mut self) {
+		self.0.fetch_add(1, Ordering::SeqCst);
+	}
+}
+
+fn is_send<T: Send>() {}
+
+#[test]
+fn test_task_storage() {
+	//任务和任务池只在负载线程安全时才能跨线程使用
+	is_send::<Task>();
+	is_send::<TaskPool>();
+	is_send::<TaskPool<Vec<u8>>>();
+
+	//清空、取消、驱逐、排空和释放任务池时，未执行的任务函数都会被释放
+	let dropped = Arc::new(AtomicUsize::new(0));
+	let ran = Arc::new(AtomicUsize::new(0));
+	let task = |dropped: &Arc<AtomicUsize>, ran: &Arc<AtomicUsize>| -> Box<FnBox() + Send> {
+		let counter = DropCounter(dropped.clone());
+		let ran = ran.clone();
+		Box::new(move || {
+			let _counter = &counter;
+			ran.fetch_add(1, Ordering::SeqCst);
+		})
+	};
+	let mut pool = TaskPool::new(2);
+	pool.set_capacity(4);
+	pool.set_full_policy(FullPolicy::EvictDelay);
+	for priority in 0..4 {
+		pool.push(TaskType::Async, priority, task(&dropped, &ran), Atom::from("storage task")).unwrap();
+	}
+	pool.push(TaskType::Sync, 10, task(&dropped, &ran), Atom::from("storage task")).unwrap();
+	assert!(dropped.load(Ordering::SeqCst) == 0); //驱逐的延迟任务由调用者在任务池锁外释放
+	drop(pool.take_released());
+	assert!(dropped.load(Ordering::SeqCst) == 1);
+	let uid = pool.push_serial(Atom::from("storage key"), TaskType::Sync, 1, task(&dropped, &ran), Atom::from("storage task"));
+	assert!(dropped.load(Ordering::SeqCst) == 1); //加入失败的任务在错误中交还给调用者
+	drop(uid.unwrap_err().into_payload());
+	assert!(dropped.load(Ordering::SeqCst) == 2);
+	let func = pool.cancel(1);
+	assert!(func.is_none()); //已被驱逐
+	drop(pool.cancel(2));
+	assert!(dropped.load(Ordering::SeqCst) == 3);
+	drop(pool.drain(3));
+	assert!(dropped.load(Ordering::SeqCst) == 4);
+	pool.resume();
+	pool.push_after(TaskType::Sync, 1, task(&dropped, &ran), Atom::from("storage task"), Duration::from_millis(1000)).unwrap();
+	let funcs = pool.clear();
+	assert!(funcs.len() == 3 && dropped.load(Ordering::SeqCst) == 4);
+	drop(funcs);
+	assert!(dropped.load(Ordering::SeqCst) == 7);
+	pool.push(TaskType::Sync, 1, task(&dropped, &ran), Atom::from("storage task")).unwrap();
+	drop(pool);
+	assert!(dropped.load(Ordering::SeqCst) == 8 && ran.load(Ordering::SeqCst) == 0);
+
+	//弹出的任务只会执行一次，执行后任务函数被释放
+	let mut pool = TaskPool::new(2);
+	pool.push(TaskType::Async, 10, task(&dropped, &ran), Atom::from("storage task")).unwrap();
+	let mut popped = Task::new();
+	assert!(pool.pop(&mut popped));
+	let mut moved = Task::new();
+	popped.move_to(&mut moved);
+	popped.run();
+	moved.run();
+	moved.run();
+	assert!(ran.load(Ordering::SeqCst) == 1 && dropped.load(Ordering::SeqCst) == 9);
+
+	//工作者执行的任务也只会执行一次
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	for _ in 0..100 {
+		push_task(&sync, TaskType::Async, |pool| pool.push(TaskType::Async, 10, task(&dropped, &ran), Atom::from("storage task"))).unwrap();
+	}
+	let worker_pool = WorkerPool::new(4, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+	assert!(ran.load(Ordering::SeqCst) == 101 && dropped.load(Ordering::SeqCst) == 109);
+}
+
+#[test]
+fn test_trace_replay() {
+	let path = std::env::temp_dir().join("pi_base_test_trace_replay.trace");
+	let executed = Arc::new(Mutex::new(Vec::new()));
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	{
+		let &(ref lock, _) = &*sync;
+		let mut pool = lock.lock().unwrap();
+		pool.set_recorder(Some(TraceRecorder::create(&path).unwrap()));
+		for index in 0..200 {
+			let executed_copy = executed.clone();
+			let func = Box::new(move || {
+				executed_copy.lock().unwrap().push(index);
+			});
+			let info = Atom::from(index.to_string());
+			let task_type = if index % 3 == 0 { TaskType::Async } else { TaskType::Sync };
+			if index % 10 == 0 {
+				pool.push_serial(Atom::from("trace key"), task_type, 5, func, info).unwrap();
+			} else {
+				pool.push(task_type, (index % 4) as u64, func, info).unwrap();
+			}
+		}
+	}
+
+	//多个工作者随机弹出任务
+	let worker_pool = WorkerPool::new(4, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+	let mut recorder = {
+		let &(ref lock, _) = &*sync;
+		lock.lock().unwrap().set_recorder(None).unwrap()
+	};
+	recorder.flush().unwrap();
+	assert!(executed.lock().unwrap().len() == 200);
+
+	//轨迹中记录了弹出任务的工作者和选择队列时的随机数
+	let replayer = Replayer::load(&path).unwrap();
+	let mut pops = Vec::new();
+	let mut draw_size = 0;
+	for event in replayer.events() {
+		if let &TraceEvent::Pop { uid, ref draws, worker, .. } = event {
+			assert!(worker.is_some());
+			draw_size += draws.len();
+			pops.push(uid);
+		}
+	}
+	assert!(pops.len() == 200 && draw_size > 0 && replayer.events().len() == 600);
+
+	//在当前线程中重放，弹出顺序与记录时相同
+	let replayed = Arc::new(Mutex::new(Vec::new()));
+	let mut pool = TaskPool::new(10);
+	let order = replayer.replay(&mut pool, |info| {
+		let replayed_copy = replayed.clone();
+		let info = info.clone();
+		let func: Box<FnBox() + Send> = Box::new(move || {
+			replayed_copy.lock().unwrap().push(info.parse::<usize>().unwrap());
 		});
+		func
+	}, |task| task.run()).unwrap();
+	assert!(order == pops);
+	assert!(pool.is_idle());
+	let replayed = replayed.lock().unwrap().clone();
+	let mut expect: Vec<usize> = order.iter().map(|uid| uid - 1).collect();
+	assert!(replayed == expect);
+	expect.sort();
+	assert!(expect == (0..200).collect::<Vec<usize>>());
+	let _ = std::fs::remove_file(&path);
+}
 
-		thread::spawn(move || {
-			let read = move |f11: Arc<AsyncFile>, result: Result<Vec<u8>>| {
-				assert!(result.is_ok());
-				println!("!!!!!!result: {:?}", result);
-				let write = move |_f11: Arc<AsyncFile>, result: Result<usize>| {
-					assert!(result.is_ok());
-				};
-				f11.pwrite(WriteOptions::SyncAll(true), 262, Vec::from("\nHello Rust1\n".as_bytes()), Box::new(write));
-			};
-			let mut buf = Vec::new();
-			buf.resize(3, 255);
-			f3.fpread(buf, 3, 0, 262, Box::new(read));
+//写入共享缓冲区的轨迹输出
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		self.0.lock().unwrap().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> Result<()> {
+		Ok(())
+	}
+}
+
+#[test]
+fn test_trace_batch() {
+	//任务池锁内只记录到缓冲区，释放取出的批次时才写入输出
+	let buffer = Arc::new(Mutex::new(Vec::new()));
+	let mut pool = TaskPool::new(10);
+	pool.set_recorder(Some(TraceRecorder::new(Box::new(SharedBuffer(buffer.clone()))).unwrap()));
+	let header = buffer.lock().unwrap().len();
+	let tagged = pool.push_tagged(TaskType::Async, 10, Box::new(|| {}), Atom::from("tagged task"), vec![Atom::from("tag a"), Atom::from("tag b")]).unwrap();
+	let sheddable = pool.push_sheddable(TaskType::Sync, 10, Box::new(|| {}), Atom::from("sheddable task")).unwrap();
+	assert!(buffer.lock().unwrap().len() == header);
+	let released = pool.take_released();
+	assert!(buffer.lock().unwrap().len() == header);
+	drop(released);
+	assert!(buffer.lock().unwrap().len() > header);
+
+	//轨迹中记录了任务的标签和是否可丢弃
+	let mut task = Task::new();
+	assert!(pool.pop_untracked(&mut task) && pool.pop_untracked(&mut task));
+	let mut recorder = pool.set_recorder(None).unwrap();
+	recorder.flush().unwrap();
+	let events = read_trace(&buffer.lock().unwrap()[..]).unwrap();
+	assert!(events.len() == 6 && recorder.count() == 6);
+	for event in events.iter() {
+		if let &TraceEvent::Push { uid, ref tags, sheddable: is_sheddable, .. } = event {
+			if uid == tagged {
+				assert!(*tags == vec![Atom::from("tag a"), Atom::from("tag b")] && !is_sheddable);
+			} else {
+				assert!(uid == sheddable && tags.is_empty() && is_sheddable);
+			}
+		}
+	}
+
+	//没有弹出任务时丢弃生成的随机数，不记录到下一次弹出
+	let buffer = Arc::new(Mutex::new(Vec::new()));
+	let mut pool = TaskPool::new(10);
+	pool.set_recorder(Some(TraceRecorder::new(Box::new(SharedBuffer(buffer.clone()))).unwrap()));
+	pool.set_rate_limit(Atom::from("limit task"), 0, 1).unwrap();
+	for _ in 0..3 {
+		pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("limit task")).unwrap();
+	}
+	assert!(pool.pop_untracked(&mut task));
+	assert!(!pool.pop_untracked(&mut task) && pool.throttled_size() == 2);
+	pool.push(TaskType::Sync, 10, Box::new(|| {}), Atom::from("other task")).unwrap();
+	assert!(pool.pop_untracked(&mut task));
+	pool.set_recorder(None).unwrap().flush().unwrap();
+	let draws: Vec<usize> = read_trace(&buffer.lock().unwrap()[..]).unwrap().iter().filter_map(|event| {
+		match event {
+			&TraceEvent::Pop { ref draws, .. } => Some(draws.len()),
+			_ => None,
+		}
+	}).collect();
+	assert!(draws.len() == 2 && draws[0] == draws[1]);
+}
+
+#[test]
+fn test_executor() {
+	fn run(seed: u64) -> Vec<usize> {
+		let order = Arc::new(Mutex::new(Vec::new()));
+		let mut executor = install_js_executor(seed).unwrap();
+		assert!(!executor.step());
+		for index in 0..50 {
+			let order_copy = order.clone();
+			let task_type = if index % 2 == 0 { TaskType::Async } else { TaskType::Sync };
+			cast_js_task(task_type, (index % 5) as u64, Box::new(move || {
+				order_copy.lock().unwrap().push(index);
+				if index % 10 == 0 {
+					//执行中加入的任务也会被执行
+					let order_copy = order_copy.clone();
+					cast_js_task(TaskType::Sync, 1, Box::new(move || {
+						order_copy.lock().unwrap().push(index + 100);
+					}), Atom::from("executor child task")).unwrap();
+				}
+			}), Atom::from("executor task")).unwrap();
+		}
+		assert!(executor.step());
+		assert!(executor.run_until_idle() == 54);
+		assert!(executor.count() == 55);
+		let order = order.lock().unwrap().clone();
+		order
+	}
+
+	//相同的种子产生相同的执行顺序
+	let order = run(7);
+	assert!(order.len() == 55);
+	assert!(order == run(7));
+
+	//任务发生异常时先完成任务再抛出异常，同一个键的串行任务可以继续执行
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let mut executor = TestExecutor::new(sync.clone(), 0).unwrap();
+	let ran = Arc::new(AtomicBool::new(false));
+	let r = ran.clone();
+	{
+		let mut pool = sync.0.lock().unwrap();
+		pool.push_serial(Atom::from("executor key"), TaskType::Sync, 10, Box::new(|| panic!("executor task panic")), Atom::from("executor task")).unwrap();
+		pool.push_serial(Atom::from("executor key"), TaskType::Sync, 10, Box::new(move || r.store(true, Ordering::SeqCst)), Atom::from("executor task")).unwrap();
+	}
+	assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| executor.step())).is_err());
+	assert!(executor.run_until_idle() == 1 && executor.count() == 2);
+	assert!(ran.load(Ordering::SeqCst) && sync.0.lock().unwrap().is_idle());
+
+	//释放测试执行器时恢复任务池的老化配置和调度随机数生成器
+	drop(executor);
+	sync.0.lock().unwrap().set_aging(Some(AgingConfig::new(Duration::from_millis(10), 1, 5)));
+	let executor = TestExecutor::new(sync.clone(), 0).unwrap();
+	assert!(sync.0.lock().unwrap().get_aging().is_none() && sync.0.lock().unwrap().is_seeded());
+	drop(executor);
+	assert!(sync.0.lock().unwrap().get_aging().is_some() && !sync.0.lock().unwrap().is_seeded());
+
+	//已有工作者在任务池上工作时不能安装测试执行器
+	let worker_pool = WorkerPool::new(1, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+	while sync.0.lock().unwrap().worker_size() == 0 {
+		thread::yield_now();
+	}
+	assert!(TestExecutor::new(sync.clone(), 0).is_err());
+
+	//后端为工作窃取任务池的全局任务池不能加入可恢复任务
+	set_js_backend(PoolBackend::Steal);
+	assert!(push_resumable(&JS_TASK_POOL, TaskType::Async, 10, Box::new(|| Resume::Done), Atom::from("resumable task")).is_err());
+	assert!(cancel_js_task(0).is_err()); //工作窃取任务池不支持取消任务
+	set_js_backend(PoolBackend::Mutex);
+}
+
+#[test]
+fn test_scope() {
+	let data: Vec<u64> = (1..1001).collect();
+	let sums = Mutex::new(Vec::new());
+
+	//没有运行工作者池时，作用域结束时在当前线程执行任务
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let r = scope(&sync, |s| {
+		for chunk in data.chunks(100) {
+			let sums = &sums;
+			s.spawn(TaskType::Async, 10, move || {
+				sums.lock().unwrap().push(chunk.iter().sum::<u64>());
+			}, Atom::from("scope task")).unwrap();
+		}
+		s.pending()
+	});
+	assert!(r == Ok(10));
+	assert!(sums.lock().unwrap().iter().sum::<u64>() == 500500);
+
+	//由工作者执行任务
+	let worker_pool = WorkerPool::new(4, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+	let mut buf = vec![0u64; 1000];
+	scope(&sync, |s| {
+		for (index, chunk) in buf.chunks_mut(100).enumerate() {
+			s.spawn(TaskType::Sync, 10, move || {
+				thread::sleep(Duration::from_millis(1));
+				for (i, n) in chunk.iter_mut().enumerate() {
+					*n = (index * 100 + i) as u64;
+				}
+			}, Atom::from("scope task")).unwrap();
+		}
+	}).unwrap();
+	assert!(buf == (0..1000).collect::<Vec<u64>>());
+
+	//任务发生异常时返回错误
+	let count = AtomicUsize::new(0);
+	let r = scope(&sync, |s| {
+		for index in 0..10 {
+			let count = &count;
+			s.spawn(TaskType::Async, 10, move || {
+				if index == 5 {
+					panic!("scope task panic");
+				}
+				count.fetch_add(1, Ordering::SeqCst);
+			}, Atom::from("scope task")).unwrap();
+		}
+	});
+	assert!(r.is_err() && count.load(Ordering::SeqCst) == 9);
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+
+	//任务未执行就在其它线程被释放时，作用域等待任务函数释放完成后才结束
+	struct SlowDrop<'a>(&'a AtomicUsize);
+	impl<'a> Drop for SlowDrop<'a> {
+		fn drop(&mut self) {
+			thread::sleep(Duration::from_millis(50));
+			self.0.fetch_add(1, Ordering::SeqCst);
+		}
+	}
+	let dropped = AtomicUsize::new(0);
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let cleared = Arc::new(AtomicBool::new(false));
+	let mut clearer = None;
+	scope(&sync, |s| {
+		let slow = SlowDrop(&dropped);
+		s.spawn(TaskType::Async, 10, move || {
+			let _slow = &slow;
+		}, Atom::from("scope task")).unwrap();
+		let sync_copy = sync.clone();
+		let cleared_copy = cleared.clone();
+		clearer = Some(thread::spawn(move || {
+			let funcs = sync_copy.0.lock().unwrap().clear();
+			cleared_copy.store(true, Ordering::SeqCst);
+			drop(funcs);
+		}));
+		while !cleared.load(Ordering::SeqCst) {
+			thread::yield_now();
+		}
+	}).unwrap();
+	assert!(dropped.load(Ordering::SeqCst) == 1);
+	clearer.unwrap().join().unwrap();
+}
+
+#[test]
+fn test_par() {
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let worker_pool = WorkerPool::new(4, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+
+	let items: Vec<u64> = (0..10000).collect();
+	let sum = AtomicUsize::new(0);
+	par_for_each(&sync, &items, |n| {
+		sum.fetch_add(*n as usize, Ordering::SeqCst);
+	});
+	assert!(sum.load(Ordering::SeqCst) == 49995000);
+
+	//结果按元素顺序返回
+	let squares = par_map(&sync, &items, |n| n * n);
+	assert!(squares == items.iter().map(|n| n * n).collect::<Vec<u64>>());
+	assert!(par_map(&sync, &Vec::<u64>::new(), |n| *n).is_empty());
+
+	//按元素顺序归约，不满足交换律的归约也能得到正确结果
+	let words: Vec<String> = (0..100).map(|n| n.to_string()).collect();
+	let joined = par_reduce(&sync, &words, |s| s.clone(), |a, b| a + &b);
+	assert!(joined == Some(words.concat()));
+	assert!(par_reduce(&sync, &Vec::<u64>::new(), |n| *n, |a, b| a + b).is_none());
+
+	//块发生异常时抛出给调用者
+	let r = std::panic::catch_unwind(|| {
+		par_for_each(&sync, &items, |n| {
+			if *n == 5000 {
+				panic!("par task panic");
+			}
 		});
+	});
+	assert!(r.is_err());
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+}
+
+#[test]
+fn test_continuation() {
+	let order = Arc::new(Mutex::new(Vec::new()));
+	let sync = Arc::new((Mutex::new(TaskPool::with_policy(10, Box::new(StrictPriorityPolicy::new()))), Condvar::new()));
+	let mut executor = TestExecutor::new(sync.clone(), 0).unwrap();
+
+	//时间片为0，每次处理一个元素后让出
+	let order_copy = order.clone();
+	let mut index = 0;
+	push_sliced(&sync, TaskType::Async, 10, Duration::from_millis(0), move |slice| {
+		while index < 3 {
+			order_copy.lock().unwrap().push(format!("slice {}", index));
+			index += 1;
+			if slice.is_expired() {
+				break;
+			}
+		}
+		index == 3
+	}, Atom::from("sliced task")).unwrap();
+	for name in vec!["a", "b"] {
+		let order_copy = order.clone();
+		sync.0.lock().unwrap().push(TaskType::Async, 10, Box::new(move || {
+			order_copy.lock().unwrap().push(name.to_string());
+		}), Atom::from("normal task")).unwrap();
+	}
+	assert!(executor.run_until_idle() == 5);
+	assert!(*order.lock().unwrap() == vec!["slice 0", "a", "b", "slice 1", "slice 2"]);
+
+	//后续部分和普通任务一样受排空模式限制，加入失败时被释放
+	let order_copy = order.clone();
+	let sync_copy = sync.clone();
+	push_resumable(&sync, TaskType::Sync, 5, Box::new(move || {
+		order_copy.lock().unwrap().push("first".to_string());
+		sync_copy.0.lock().unwrap().drain(0);
+		Resume::Yield(Box::new(move || {
+			order_copy.lock().unwrap().push("second".to_string());
+			Resume::Done
+		}))
+	}), Atom::from("resumable task")).unwrap();
+	assert!(executor.step());
+	{
+		let mut pool = executor.get_pool().0.lock().unwrap();
+		assert!(pool.is_drain() && pool.size() == 0);
+		pool.resume();
+	}
+	assert!(executor.run_until_idle() == 0);
+	assert!(order.lock().unwrap()[5..] == ["first".to_string()]);
+
+	//后续部分保留原任务的标签、是否可丢弃和上下文
+	let context = TaskContext::with_new_trace();
+	let trace_id = context.get_trace_id();
+	let old = set_current(Some(context));
+	push_resumable_with(&sync, TaskType::Async, 10, Box::new(|| {
+		Resume::Yield(Box::new(|| Resume::Done))
+	}), Atom::from("resumable task"), vec![Atom::from("resumable tag")], true).unwrap();
+	set_current(old);
+	assert!(executor.step());
+	let mut task = Task::new();
+	assert!(sync.0.lock().unwrap().pop_untracked(&mut task));
+	assert!(task.has_tag(&Atom::from("resumable tag")) && task.is_sheddable());
+	assert!(task.get_context().map(|c| c.get_trace_id()) == Some(trace_id));
+	task.run();
+}
+
+#[test]
+fn test_exclusive_task() {
+	let active = Arc::new(AtomicUsize::new(0));
+	let violated = Arc::new(AtomicBool::new(false));
+	let order = Arc::new(Mutex::new(Vec::new()));
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let worker_pool = WorkerPool::new(4, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+
+	let push = |task_type: TaskType, name: usize| {
+		let active_copy = active.clone();
+		let violated_copy = violated.clone();
+		let order_copy = order.clone();
+		push_task(&sync, task_type, move |pool| pool.push(task_type, 10, Box::new(move || {
+			let count = active_copy.fetch_add(1, Ordering::SeqCst);
+			if let TaskType::Exclusive = task_type {
+				//独占任务执行时没有其它任务在执行
+				if count != 0 {
+					violated_copy.store(true, Ordering::SeqCst);
+				}
+			}
+			order_copy.lock().unwrap().push(name);
+			thread::sleep(Duration::from_millis(2));
+			active_copy.fetch_sub(1, Ordering::SeqCst);
+		}), Atom::from("exclusive test task"))).unwrap();
 	};
-	AsyncFile::open(PathBuf::from(r"foo0.txt"), AsynFileOptions::ReadWrite(1), Box::new(open));
-	thread::sleep(Duration::from_millis(5000));
-	
-	let rename = move |from, to, result: Result<()>| {
-		assert!(result.is_ok());
-		let remove = move |result: Result<()>| {
-			assert!(result.is_ok());
-		};
-		AsyncFile::remove(PathBuf::from(r"foo0.swap"), Box::new(remove));
-	};
-	AsyncFile::rename(PathBuf::from(r"foo0.txt"), PathBuf::from(r"foo0.swap"), Box::new(rename));
-	thread::sleep(Duration::from_millis(1000));
+	for name in 0..20 {
+		push(TaskType::Async, name);
+	}
+	push(TaskType::Exclusive, 100);
+	for name in 20..40 {
+		push(TaskType::Sync, name);
+	}
+	push(TaskType::Exclusive, 101);
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+	assert!(!violated.load(Ordering::SeqCst));
+
+	//独占任务加入后暂停分派，之后加入的任务在独占任务完成后才执行
+	let order = order.lock().unwrap().clone();
+	assert!(order.len() == 42);
+	let first = order.iter().position(|&name| name == 100).unwrap();
+	let second = order.iter().position(|&name| name == 101).unwrap();
+	assert!(order[..first].iter().all(|&name| name < 20) && first < second);
+	{
+		let &(ref lock, _) = &*sync;
+		let pool = lock.lock().unwrap();
+		assert!(pool.wait_histogram(TaskType::Exclusive).unwrap().count() == 2);
+		assert!(!pool.is_exclusive() && pool.exclusive_size() == 0);
+	}
+	assert!(StealPool::new().push(TaskType::Exclusive, 10, Box::new(|| {}), Atom::from("exclusive test task")).is_err());
+
+	//弹出的任务完成前不弹出独占任务，不完成任务的调用者使用pop_untracked，不阻塞独占任务
+	let mut pool = TaskPool::new(10);
+	let mut task = Task::new();
+	pool.push(TaskType::Async, 10, Box::new(|| {}), Atom::from("async")).unwrap();
+	assert!(pool.pop(&mut task) && pool.running_size() == 1);
+	pool.push(TaskType::Exclusive, 10, Box::new(|| {}), Atom::from("exclusive")).unwrap();
+	pool.push(TaskType::Async, 10, Box::new(|| {}), Atom::from("async")).unwrap();
+	assert!(!pool.pop(&mut Task::new()));
+	assert!(pool.complete(&mut task) && pool.running_size() == 0);
+	task.reset();
+	assert!(pool.pop_untracked(&mut task) && task.get_info() == "exclusive" && !pool.is_exclusive());
+	task.reset();
+	assert!(pool.pop_untracked(&mut task) && task.get_info() == "async" && pool.is_idle());
+}
+
+#[test]
+fn test_task_context() {
+	fn record(seen: &Arc<Mutex<Vec<(&'static str, Option<u64>, Option<Atom>)>>>, name: &'static str) {
+		let context = current();
+		let trace_id = context.as_ref().map(|c| c.get_trace_id());
+		let value = context.as_ref().and_then(|c| c.get(&Atom::from("request")).cloned());
+		seen.lock().unwrap().push((name, trace_id, value));
+	}
+
+	let seen = Arc::new(Mutex::new(Vec::new()));
+	let sync = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let other = Arc::new((Mutex::new(TaskPool::new(10)), Condvar::new()));
+	let worker_pool = WorkerPool::new(1, 1024 * 1024, 10000);
+	worker_pool.run(sync.clone());
+	let other_worker_pool = WorkerPool::new(1, 1024 * 1024, 10000);
+	other_worker_pool.run(other.clone());
+	let ext_worker_pool = WorkerPool::new(1, 1024 * 1024, 10000);
+	ext_worker_pool.run(EXT_TASK_POOL.clone());
+
+	//加入任务时捕获上下文，任务中投递的任务和未来任务继续使用该上下文
+	let context = TaskContext::with_new_trace().with_value(Atom::from("request"), Atom::from("login"));
+	let trace_id = context.get_trace_id();
+	assert!(set_current(Some(context)).is_none());
+	let seen_copy = seen.clone();
+	let other_copy = other.clone();
+	push_task(&sync, TaskType::Async, move |pool| pool.push(TaskType::Async, 10, Box::new(move || {
+		record(&seen_copy, "task");
+		let seen_child = seen_copy.clone();
+		push_task(&other_copy, TaskType::Sync, move |pool| pool.push(TaskType::Sync, 10, Box::new(move || {
+			record(&seen_child, "child");
+		}), Atom::from("context child task"))).unwrap();
+		let seen_future = seen_copy.clone();
+		FutTaskPool::new(cast_ext_task).spawn::<(), ()>(Box::new(move |_, _, _, _| {
+			record(&seen_future, "future");
+		}), 1000).unwrap();
+	}), Atom::from("context task"))).unwrap();
+	assert!(set_current(None).is_some());
+
+	//没有上下文的任务执行时，工作者线程的上下文已恢复
+	let seen_copy = seen.clone();
+	push_task(&sync, TaskType::Async, move |pool| pool.push(TaskType::Async, 10, Box::new(move || {
+		record(&seen_copy, "plain");
+	}), Atom::from("context task"))).unwrap();
+	assert!(wait_idle(&sync, Duration::from_millis(5000)));
+	assert!(wait_idle(&other, Duration::from_millis(5000)));
+	assert!(wait_idle(&EXT_TASK_POOL, Duration::from_millis(5000)));
+
+	let mut seen = seen.lock().unwrap().clone();
+	seen.sort_by_key(|&(name, _, _)| name);
+	let value = Some(Atom::from("login"));
+	assert!(seen == vec![("child", Some(trace_id), value.clone()), ("future", Some(trace_id), value.clone()), ("plain", None, None), ("task", Some(trace_id), value)]);
 }
 
 #[test]